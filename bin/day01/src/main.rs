@@ -1,4 +1,6 @@
 use color_eyre::eyre::{eyre, Result};
+use common::parse::parse_all;
+use common::parsers::{groups, number};
 use common::select_and_solve;
 
 fn main() -> Result<()> {
@@ -9,20 +11,11 @@ fn main() -> Result<()> {
 
 #[allow(dead_code)]
 fn part1(input: Vec<String>) -> Result<String> {
-    let mut sum = 0;
-    let mut max = 0;
-    for line in input {
-        if line.is_empty() {
-            if sum > max {
-                max = sum;
-            }
-            sum = 0;
-        } else {
-            sum += line.parse::<i32>()?;
-        }
-    }
-
-    Ok(max.to_string())
+    let most = elf_totals(&input)?
+        .into_iter()
+        .max()
+        .ok_or(eyre!("no elves"))?;
+    Ok(most.to_string())
 }
 
 // https://fasterthanli.me/series/advent-of-code-2022/part-1
@@ -42,20 +35,9 @@ fn part1_alt(input: Vec<String>) -> Result<String> {
 
 #[allow(dead_code)]
 fn part2(input: Vec<String>) -> Result<String> {
-    let mut sum = 0;
-    let mut values = vec![];
-    for line in input {
-        if line.is_empty() {
-            values.push(sum);
-            sum = 0;
-        } else {
-            sum += line.parse::<i32>()?;
-        }
-    }
-
-    values.sort();
-    values.reverse();
-    Ok((values[0] + values[1] + values[2]).to_string())
+    let mut totals = elf_totals(&input)?;
+    totals.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(totals.iter().take(3).sum::<u64>().to_string())
 }
 
 // https://fasterthanli.me/series/advent-of-code-2022/part-1
@@ -74,6 +56,13 @@ fn part2_alt(input: Vec<String>) -> Result<String> {
     Ok(answer.to_string())
 }
 
+// Total calories carried by each elf
+fn elf_totals(input: &[String]) -> Result<Vec<u64>> {
+    let text = input.join("\n");
+    let elves = parse_all(groups(number::<u64>), text.trim_end())?;
+    Ok(elves.iter().map(|items| items.iter().sum()).collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,4 +99,11 @@ mod tests {
     fn test_part2(input: Vec<String>) {
         assert_eq!(part2(input).unwrap(), "45000");
     }
+
+    #[rstest]
+    fn test_elf_totals_error(mut input: Vec<String>) {
+        input[5] = "50OO".to_owned();
+        let e = elf_totals(&input).unwrap_err().to_string();
+        assert!(e.contains("bad input at line 6, column 3"), "{e}");
+    }
 }
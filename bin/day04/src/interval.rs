@@ -0,0 +1,300 @@
+// Inclusive ranges of section IDs, and sets of them.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Interval {
+    pub start: usize,
+    pub end: usize, // inclusive
+}
+
+impl Interval {
+    // The end must be below usize::MAX, so that the length and the section
+    // just after the end can always be worked out
+    pub fn new(start: usize, end: usize) -> Option<Self> {
+        (start <= end && end < usize::MAX).then_some(Interval { start, end })
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start + 1
+    }
+
+    pub fn contains(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    // Overlapping, or next to each other with no gap
+    fn touches(&self, other: &Interval) -> bool {
+        self.start <= other.end.saturating_add(1) && other.start <= self.end.saturating_add(1)
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        Interval::new(self.start.max(other.start), self.end.min(other.end))
+    }
+
+    pub fn union(&self, other: &Interval) -> IntervalSet {
+        IntervalSet::from_iter([*self, *other])
+    }
+
+    pub fn difference(&self, other: &Interval) -> IntervalSet {
+        let mut result = IntervalSet::new();
+        if !self.overlaps(other) {
+            result.insert(*self);
+            return result;
+        }
+        if self.start < other.start {
+            result.insert(Interval {
+                start: self.start,
+                end: other.start - 1,
+            });
+        }
+        if other.end < self.end {
+            result.insert(Interval {
+                start: other.end + 1,
+                end: self.end,
+            });
+        }
+        result
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+// Disjoint intervals, kept sorted, with any that overlap or touch merged
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    intervals: Vec<Interval>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, interval: Interval) {
+        // everything that touches the new interval is merged into it
+        let first = self
+            .intervals
+            .partition_point(|i| i.end.saturating_add(1) < interval.start);
+        let last = first
+            + self.intervals[first..]
+                .iter()
+                .take_while(|i| i.touches(&interval))
+                .count();
+
+        let merged = self.intervals[first..last]
+            .iter()
+            .fold(interval, |acc, i| Interval {
+                start: acc.start.min(i.start),
+                end: acc.end.max(i.end),
+            });
+        self.intervals.splice(first..last, [merged]);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval> {
+        self.intervals.iter()
+    }
+
+    // Total number of sections covered
+    pub fn coverage(&self) -> usize {
+        self.intervals.iter().map(Interval::len).sum()
+    }
+
+    // From the lowest section in the set to the highest
+    pub fn hull(&self) -> Option<Interval> {
+        Some(Interval {
+            start: self.intervals.first()?.start,
+            end: self.intervals.last()?.end,
+        })
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        self.iter().chain(other.iter()).copied().collect()
+    }
+
+    pub fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        let mut result = self.clone();
+        for b in other.iter() {
+            result = result
+                .iter()
+                .flat_map(|a| a.difference(b).intervals)
+                .collect();
+        }
+        result
+    }
+
+    // The sections within the hull that are not in the set
+    pub fn gaps(&self) -> IntervalSet {
+        match self.hull() {
+            Some(hull) => IntervalSet::from_iter([hull]).difference(self),
+            None => IntervalSet::new(),
+        }
+    }
+}
+
+impl FromIterator<Interval> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = Interval>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        for interval in iter {
+            set.insert(interval);
+        }
+        set
+    }
+}
+
+impl fmt::Display for IntervalSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.intervals.iter().map(|i| i.to_string()).collect();
+        write!(f, "{{{}}}", parts.join(", "))
+    }
+}
+
+// Split the sections covered by any of the intervals into maximal pieces
+// covered by the same number of intervals, with that number. Pieces covered
+// by nothing are left out.
+pub fn coverage_counts(intervals: &[Interval]) -> Vec<(Interval, usize)> {
+    // +1 where an interval starts, -1 just after it ends
+    let mut events: Vec<(usize, isize)> = intervals
+        .iter()
+        .flat_map(|i| [(i.start, 1), (i.end + 1, -1)])
+        .collect();
+    events.sort();
+
+    let mut pieces: Vec<(Interval, usize)> = vec![];
+    let mut depth = 0;
+    let mut events = events.into_iter().peekable();
+    while let Some((at, change)) = events.next() {
+        depth += change;
+        // apply every event at the same position before emitting a piece
+        let Some(&(next, _)) = events.peek() else {
+            break;
+        };
+        if next == at || depth == 0 {
+            continue;
+        }
+
+        let depth = depth as usize;
+        match pieces.last_mut() {
+            // one interval ends just as another begins
+            Some((last, count)) if *count == depth && last.end + 1 == at => last.end = next - 1,
+            _ => pieces.push((
+                Interval {
+                    start: at,
+                    end: next - 1,
+                },
+                depth,
+            )),
+        }
+    }
+
+    pieces
+}
+
+// Sections covered by exactly `n` of the intervals
+pub fn covered_by_exactly(intervals: &[Interval], n: usize) -> IntervalSet {
+    coverage_counts(intervals)
+        .into_iter()
+        .filter(|&(_, count)| count == n)
+        .map(|(interval, _)| interval)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn iv(start: usize, end: usize) -> Interval {
+        Interval::new(start, end).unwrap()
+    }
+
+    fn set(intervals: &[(usize, usize)]) -> IntervalSet {
+        intervals.iter().map(|&(s, e)| iv(s, e)).collect()
+    }
+
+    #[test]
+    fn test_interval() {
+        assert_eq!(Interval::new(4, 2), None);
+        assert_eq!(Interval::new(4, usize::MAX), None);
+        assert_eq!(iv(0, usize::MAX - 1).len(), usize::MAX);
+        assert_eq!(iv(2, 4).len(), 3);
+        assert!(iv(2, 8).contains(&iv(3, 7)));
+        assert!(!iv(3, 7).contains(&iv(2, 8)));
+        assert!(iv(5, 7).overlaps(&iv(7, 9)));
+        assert!(!iv(2, 4).overlaps(&iv(6, 8)));
+        assert_eq!(iv(2, 6).intersection(&iv(4, 8)), Some(iv(4, 6)));
+        assert_eq!(iv(2, 3).intersection(&iv(4, 5)), None);
+    }
+
+    #[test]
+    fn test_interval_union() {
+        assert_eq!(iv(2, 4).union(&iv(3, 8)), set(&[(2, 8)]));
+        assert_eq!(iv(2, 4).union(&iv(5, 8)), set(&[(2, 8)]));
+        assert_eq!(iv(2, 4).union(&iv(6, 8)), set(&[(2, 4), (6, 8)]));
+    }
+
+    #[test]
+    fn test_interval_difference() {
+        assert_eq!(iv(2, 8).difference(&iv(4, 5)), set(&[(2, 3), (6, 8)]));
+        assert_eq!(iv(2, 8).difference(&iv(0, 5)), set(&[(6, 8)]));
+        assert_eq!(iv(2, 8).difference(&iv(1, 9)), IntervalSet::new());
+        assert_eq!(iv(2, 8).difference(&iv(9, 9)), set(&[(2, 8)]));
+        assert_eq!(iv(0, 8).difference(&iv(0, 0)), set(&[(1, 8)]));
+    }
+
+    #[test]
+    fn test_interval_set_insert() {
+        let s = set(&[(10, 12), (2, 4), (6, 6), (5, 5), (20, 30), (11, 21)]);
+        assert_eq!(s.to_string(), "{2-6, 10-30}");
+        assert_eq!(s.coverage(), 5 + 21);
+        assert_eq!(s.hull(), Some(iv(2, 30)));
+        assert_eq!(s.gaps(), set(&[(7, 9)]));
+    }
+
+    #[test]
+    fn test_interval_set_algebra() {
+        let a = set(&[(1, 5), (10, 15)]);
+        let b = set(&[(4, 11), (20, 20)]);
+        assert_eq!(a.union(&b), set(&[(1, 15), (20, 20)]));
+        assert_eq!(a.difference(&b), set(&[(1, 3), (12, 15)]));
+        assert_eq!(b.difference(&a), set(&[(6, 9), (20, 20)]));
+    }
+
+    #[test]
+    fn test_coverage_counts() {
+        let intervals = [iv(2, 4), iv(3, 6), iv(6, 6), iv(9, 9)];
+        assert_eq!(
+            coverage_counts(&intervals),
+            vec![
+                (iv(2, 2), 1),
+                (iv(3, 4), 2),
+                (iv(5, 5), 1),
+                (iv(6, 6), 2),
+                (iv(9, 9), 1),
+            ]
+        );
+        assert_eq!(
+            covered_by_exactly(&intervals, 1),
+            set(&[(2, 2), (5, 5), (9, 9)])
+        );
+        assert_eq!(covered_by_exactly(&intervals, 2), set(&[(3, 4), (6, 6)]));
+        assert_eq!(coverage_counts(&[]), vec![]);
+        assert_eq!(
+            coverage_counts(&[iv(5, usize::MAX - 1)]),
+            vec![(iv(5, usize::MAX - 1), 1)]
+        );
+
+        // adjacent pieces with the same count are merged
+        assert_eq!(
+            coverage_counts(&[iv(2, 4), iv(5, 6), iv(6, 6)]),
+            vec![(iv(2, 5), 1), (iv(6, 6), 2)]
+        );
+    }
+}
@@ -1,139 +1,88 @@
 // Let's learn something new: "nom"
 
-use color_eyre::eyre::Result;
-use common::select_and_solve;
-
-// nom imports
-use nom::{
-    bytes::complete::{tag, take_while},
-    character::complete::{char, digit1},
-    combinator::map_res,
-    error::Error,
-    sequence::{separated_pair, tuple},
-    Finish, IResult,
-};
+mod interval;
+mod parse;
+
+use crate::interval::{covered_by_exactly, Interval, IntervalSet};
+use crate::parse::{parse_interval, parse_line, parse_lines};
+use color_eyre::eyre::{Report, Result};
+use common::parse::parse_all;
+use common::{load_input, select_and_solve};
+use std::env;
 use std::str::FromStr;
 
 fn main() -> Result<()> {
     color_eyre::install()?;
-    select_and_solve("inputs/day04.1", part1, "inputs/day04.2", part2)?;
-    Ok(())
-}
 
-// will recognize "NNN", "NNN" in "NNN-NNN"
-fn parse_range(input: &str) -> IResult<&str, (&str, &str)> {
-    let (i, start) = take_while(|c: char| c.is_numeric())(input)?;
-    let (i, _) = tag("-")(i)?;
-    let (i, end) = take_while(|c: char| c.is_numeric())(i)?;
-    Ok((i, (start, end)))
-}
+    // day04 coverage [filename]
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("coverage") {
+        let filename = args.get(2).map_or("inputs/day04.1", String::as_str);
+        return coverage(load_input(filename)?);
+    }
 
-// will recognize "XXX", "XXX" in "XXX,XXX"
-fn parse_line(input: &str) -> IResult<&str, (&str, &str)> {
-    let (i, first) = take_while(|c: char| c != ',')(input)?;
-    let (i, _) = tag(",")(i)?;
-    let (i, second) = take_while(|c: char| c != ',')(i)?;
-    Ok((i, (first, second)))
+    select_and_solve("inputs/day04.1", part1, "inputs/day04.2", part2)?;
+    Ok(())
 }
 
-#[derive(Debug)]
-struct Range {
-    start: usize,
-    end: usize, // inclusive
+// Which sections are covered by nobody, or by only one elf, across all pairs
+fn coverage(input: Vec<String>) -> Result<()> {
+    let lines = get_lines(input)?;
+    let assignments: Vec<Interval> = lines
+        .iter()
+        .flat_map(|line| [line.first, line.second])
+        .collect();
+    let covered = lines
+        .iter()
+        .map(|line| line.first.union(&line.second))
+        .fold(IntervalSet::new(), |covered, pair| covered.union(&pair));
+    let shared: IntervalSet = lines
+        .iter()
+        .filter_map(|line| line.first.intersection(&line.second))
+        .collect();
+
+    println!("covered: {} sections {covered}", covered.coverage());
+    println!("covered by both elves of a pair: {shared}");
+    println!("covered by nobody: {}", covered.gaps());
+    println!(
+        "covered by exactly one elf: {}",
+        covered_by_exactly(&assignments, 1)
+    );
+    Ok(())
 }
 
-impl FromStr for Range {
-    type Err = Error<String>;
+impl FromStr for Interval {
+    type Err = Report;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match parse_range(s).finish() {
-            Ok((_remaining, (start, end))) => Ok(Range {
-                start: str::parse::<usize>(start).expect("should be convertible"),
-                end: str::parse::<usize>(end).expect("should be convertible"),
-            }),
-            Err(Error { input, code }) => Err(Error {
-                input: input.to_string(),
-                code,
-            }),
-        }
+        parse_all(parse_interval, s)
     }
 }
 
 #[derive(Debug)]
 struct Line {
-    first: Range,
-    second: Range,
+    first: Interval,
+    second: Interval,
 }
 
 impl FromStr for Line {
-    type Err = Error<String>;
+    type Err = Report;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match parse_line(s).finish() {
-            Ok((_remaining, (first, second))) => Ok(Line {
-                first: Range::from_str(first).expect("should be convertible"),
-                second: Range::from_str(second).expect("should be convertible"),
-            }),
-            Err(Error { input, code }) => Err(Error {
-                input: input.to_string(),
-                code,
-            }),
-        }
+        parse_all(parse_line, s)
     }
 }
 
-// Now let's try a simpler parser:
-fn nom_parse(s: &str) -> IResult<&str, Line> {
-    // Since we wish to use 'position' multiple times in the parser, we need to
-    // implement our own copy via a function wrapper:
-    // https://stackoverflow.com/questions/70236597/why-cant-i-use-the-same-parser-twice-in-a-tuple
-    //let mut position = map_res(digit1, |s: &str| s.parse::<usize>());
-    fn position(input: &str) -> IResult<&str, usize> {
-        map_res(digit1, |s: &str| s.parse::<usize>())(input)
-    }
-
-    let (s, ((pos1, pos2), _, (pos3, pos4))) = tuple((
-        separated_pair(position, char('-'), position),
-        char(','),
-        separated_pair(position, char('-'), position),
-    ))(s)?;
-
-    // Or we can use nested seperated_pair calls:
-    // let (s, (((pos1, pos2), (pos3, pos4)),)) = tuple((separated_pair(
-    //     separated_pair(position, char('-'), position),
-    //     char(','),
-    //     separated_pair(position, char('-'), position),
-    // ),))(s)?;
-
-    Ok((
-        s,
-        Line {
-            first: Range {
-                start: pos1,
-                end: pos2,
-            },
-            second: Range {
-                start: pos3,
-                end: pos4,
-            },
-        },
-    ))
-}
-
 fn get_lines(input: Vec<String>) -> Result<Vec<Line>> {
-    let lines_results: Vec<IResult<_, Line>> = input.iter().map(|s| nom_parse(s)).collect();
-
-    let lines: Vec<Line> = lines_results.into_iter().map(|x| x.unwrap().1).collect();
-    Ok(lines)
+    // parse as a whole, so errors can refer to line numbers
+    let text = input.join("\n");
+    parse_all(parse_lines, text.trim_end())
 }
 
 fn part1(input: Vec<String>) -> Result<String> {
     let num = get_lines(input)?
         .iter()
-        .filter(|x| {
-            (x.first.start >= x.second.start && x.first.end <= x.second.end)
-                || (x.second.start >= x.first.start && x.second.end <= x.first.end)
-        })
+        .filter(|x| x.second.contains(&x.first) || x.first.contains(&x.second))
         .count();
 
     Ok(num.to_string())
@@ -142,7 +91,7 @@ fn part1(input: Vec<String>) -> Result<String> {
 fn part2(input: Vec<String>) -> Result<String> {
     let num = get_lines(input)?
         .iter()
-        .filter(|x| (x.first.start <= x.second.end && x.first.end >= x.second.start))
+        .filter(|x| x.first.overlaps(&x.second))
         .count();
 
     Ok(num.to_string())
@@ -151,6 +100,8 @@ fn part2(input: Vec<String>) -> Result<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::parse::Span;
+    use nom_supreme::error::ErrorTree;
     use rstest::*;
 
     #[fixture]
@@ -180,7 +131,7 @@ mod tests {
 
     #[test]
     fn test_parse_range() {
-        let r = Range::from_str("123-456").unwrap();
+        let r = Interval::from_str("123-456").unwrap();
         dbg!(&r);
         assert_eq!(r.start, 123);
         assert_eq!(r.end, 456);
@@ -198,11 +149,26 @@ mod tests {
 
     #[test]
     fn test_nom_parse() {
-        let (_, l) = nom_parse("123-456,789-1012").unwrap();
+        let (_, l) = parse_line::<ErrorTree<Span>>("123-456,789-1012".into()).unwrap();
         dbg!(&l);
         assert_eq!(l.first.start, 123);
         assert_eq!(l.first.end, 456);
         assert_eq!(l.second.start, 789);
         assert_eq!(l.second.end, 1012);
     }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Interval::from_str("456-123").is_err());
+        assert!(Interval::from_str("123-456x").is_err());
+        assert!(Line::from_str("1-2,3-4,5-6").is_err());
+        assert!(Line::from_str("1-2").is_err());
+    }
+
+    #[rstest]
+    fn test_get_lines_error(mut input: Vec<String>) {
+        input[2] = "5-7,9-7".to_owned();
+        let e = get_lines(input).unwrap_err().to_string();
+        assert!(e.contains("bad input at line 3, column 5"), "{e}");
+    }
 }
@@ -0,0 +1,145 @@
+// Parsing the section assignments. Run with common::parse::parse_all() to get
+// errors that point at where the input went wrong.
+
+use crate::interval::Interval;
+use crate::Line;
+use common::parse::Span;
+use nom::character::complete::{char, digit1, newline};
+use nom::combinator::{cut, map_res};
+use nom::error::{ErrorKind, FromExternalError, ParseError};
+use nom::multi::separated_list1;
+use nom::sequence::{preceded, separated_pair};
+use nom::IResult;
+use std::num::ParseIntError;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RangeError {
+    #[error("range starts at {start} but ends at {end}")]
+    Backwards { start: usize, end: usize },
+
+    #[error("section {0} is too large")]
+    TooLarge(usize),
+}
+
+fn parse_position<'a, E>(i: Span<'a>) -> IResult<Span<'a>, usize, E>
+where
+    E: ParseError<Span<'a>> + FromExternalError<Span<'a>, ParseIntError>,
+{
+    map_res(digit1, |s: Span| s.fragment().parse::<usize>())(i)
+}
+
+// "NNN-NNN", where the first number is no larger than the second
+pub fn parse_interval<'a, E>(i: Span<'a>) -> IResult<Span<'a>, Interval, E>
+where
+    E: ParseError<Span<'a>>
+        + FromExternalError<Span<'a>, ParseIntError>
+        + FromExternalError<Span<'a>, RangeError>,
+{
+    // once we've seen a number, this must be a range
+    let (rest, (start, end)) =
+        separated_pair(parse_position, cut(char('-')), cut(parse_position))(i)?;
+
+    let error = match Interval::new(start, end) {
+        Some(interval) => return Ok((rest, interval)),
+        None if start > end => RangeError::Backwards { start, end },
+        None => RangeError::TooLarge(end),
+    };
+    Err(nom::Err::Failure(E::from_external_error(
+        i,
+        ErrorKind::Verify,
+        error,
+    )))
+}
+
+// "NNN-NNN,NNN-NNN"
+pub fn parse_line<'a, E>(i: Span<'a>) -> IResult<Span<'a>, Line, E>
+where
+    E: ParseError<Span<'a>>
+        + FromExternalError<Span<'a>, ParseIntError>
+        + FromExternalError<Span<'a>, RangeError>,
+{
+    let (i, first) = parse_interval(i)?;
+    let (i, second) = preceded(cut(char(',')), cut(parse_interval))(i)?;
+    Ok((i, Line { first, second }))
+}
+
+pub fn parse_lines<'a, E>(i: Span<'a>) -> IResult<Span<'a>, Vec<Line>, E>
+where
+    E: ParseError<Span<'a>>
+        + FromExternalError<Span<'a>, ParseIntError>
+        + FromExternalError<Span<'a>, RangeError>,
+{
+    // every newline must be followed by another line
+    separated_list1(newline, cut(parse_line))(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::parse::parse_all;
+    use nom_supreme::error::ErrorTree;
+
+    fn error(input: &str) -> String {
+        parse_all(parse_lines, input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        let (rest, interval) = parse_interval::<ErrorTree<Span>>("2-4,".into()).unwrap();
+        assert_eq!(interval, Interval::new(2, 4).unwrap());
+        assert_eq!(*rest.fragment(), ",");
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let lines = parse_all(parse_lines, "2-4,6-8\n2-3,4-5").unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].second, Interval::new(4, 5).unwrap());
+    }
+
+    #[test]
+    fn test_bad_number() {
+        let e = error("2-4,6-8\n2-x,4-5");
+        assert!(e.contains("bad input at line 2, column 3"), "{e}");
+        assert!(e.contains("expected an ascii digit"), "{e}");
+    }
+
+    #[test]
+    fn test_backwards_range() {
+        let e = error("2-4,6-8\n2-3,5-4");
+        assert!(e.contains("bad input at line 2, column 5"), "{e}");
+        assert!(e.contains("range starts at 5 but ends at 4"), "{e}");
+    }
+
+    #[test]
+    fn test_too_large() {
+        let e = error(&format!("2-4,6-{}", usize::MAX));
+        assert!(e.contains("bad input at line 1, column 5"), "{e}");
+        assert!(
+            e.contains(&format!("section {} is too large", usize::MAX)),
+            "{e}"
+        );
+        assert!(parse_all(parse_lines, &format!("2-4,6-{}", usize::MAX - 1)).is_ok());
+    }
+
+    #[test]
+    fn test_trailing_input() {
+        let e = error("2-4,6-8 \n2-3,4-5");
+        assert!(e.contains("bad input at line 1, column 8"), "{e}");
+
+        let e = error("2-4,6-8\n2-3,4-5,6-7");
+        assert!(e.contains("bad input at line 2, column 8"), "{e}");
+    }
+
+    #[test]
+    fn test_missing_line() {
+        let e = error("2-4,6-8\n\n2-3,4-5");
+        assert!(e.contains("bad input at line 2, column 1"), "{e}");
+    }
+
+    #[test]
+    fn test_overflow() {
+        let e = error("2-4,6-99999999999999999999999");
+        assert!(e.contains("number too large"), "{e}");
+    }
+}
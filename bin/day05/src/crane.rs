@@ -0,0 +1,196 @@
+// Crane models. Each one decides how the crates of a single move are carried
+// from the source stack to the destination stack.
+
+use crate::validate::{check_move, MoveError};
+use crate::{Move, State};
+use color_eyre::eyre::{eyre, Result};
+
+pub trait Crane {
+    fn name(&self) -> String;
+
+    // Assumes the move has already been checked with validate::check_move
+    fn do_move(&self, state: &mut State, mv: &Move);
+
+    // Stops at the first impossible move, leaving the earlier moves applied
+    fn do_moves(&self, state: &mut State, moves: &[Move]) -> Result<(), MoveError> {
+        for (index, mv) in moves.iter().enumerate() {
+            check_move(index, mv, &state.heights())?;
+            self.do_move(state, mv);
+        }
+        Ok(())
+    }
+}
+
+// Part 1: moves one crate at a time, so a multi-crate move reverses their order
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn name(&self) -> String {
+        "CrateMover 9000".to_owned()
+    }
+
+    fn do_move(&self, state: &mut State, mv: &Move) {
+        state.move_crates(mv, mv.count, false);
+    }
+}
+
+// Part 2: moves all crates at once, preserving their order
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn name(&self) -> String {
+        "CrateMover 9001".to_owned()
+    }
+
+    fn do_move(&self, state: &mut State, mv: &Move) {
+        state.move_crates(mv, mv.count, true);
+    }
+}
+
+// Moves up to `capacity` crates at a time, preserving the order within each batch
+pub struct BatchCrane {
+    pub capacity: u32,
+}
+
+impl Crane for BatchCrane {
+    fn name(&self) -> String {
+        format!("batch crane (capacity {})", self.capacity)
+    }
+
+    fn do_move(&self, state: &mut State, mv: &Move) {
+        let mut remaining = mv.count;
+        while remaining > 0 {
+            let n = remaining.min(self.capacity);
+            state.move_crates(mv, n, true);
+            remaining -= n;
+        }
+    }
+}
+
+// Like BatchCrane, but a batch with fewer than `capacity` crates is unstable
+// and ends up upside down.
+pub struct ReversingCrane {
+    pub capacity: u32,
+}
+
+impl Crane for ReversingCrane {
+    fn name(&self) -> String {
+        format!("reversing crane (capacity {})", self.capacity)
+    }
+
+    fn do_move(&self, state: &mut State, mv: &Move) {
+        let mut remaining = mv.count;
+        while remaining > 0 {
+            let n = remaining.min(self.capacity);
+            state.move_crates(mv, n, n == self.capacity);
+            remaining -= n;
+        }
+    }
+}
+
+// Look up a crane by the name given on the command line:
+//   9000, 9001, batch:K, reversing:K
+pub fn crane_by_name(name: &str) -> Result<Box<dyn Crane>> {
+    let capacity = |s: &str| -> Result<u32> {
+        match s.parse::<u32>()? {
+            0 => Err(eyre!("crane capacity must be at least 1")),
+            k => Ok(k),
+        }
+    };
+
+    let crane: Box<dyn Crane> = match name.split_once(':') {
+        None if name == "9000" => Box::new(CrateMover9000),
+        None if name == "9001" => Box::new(CrateMover9001),
+        Some(("batch", k)) => Box::new(BatchCrane {
+            capacity: capacity(k)?,
+        }),
+        Some(("reversing", k)) => Box::new(ReversingCrane {
+            capacity: capacity(k)?,
+        }),
+        _ => return Err(eyre!("Invalid crane {name}")),
+    };
+    Ok(crane)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[fixture]
+    fn state() -> State {
+        State::with_stacks(vec![vec!['A', 'B', 'C', 'D', 'E'], vec![]])
+    }
+
+    fn moved(crane: &dyn Crane, mut state: State, count: u32) -> Vec<char> {
+        crane.do_move(&mut state, &Move::new(count, 1, 2));
+        state.stacks[1].iter().copied().collect()
+    }
+
+    #[rstest]
+    fn test_crate_mover_9000(state: State) {
+        assert_eq!(moved(&CrateMover9000, state, 3), vec!['E', 'D', 'C']);
+    }
+
+    #[rstest]
+    fn test_crate_mover_9001(state: State) {
+        assert_eq!(moved(&CrateMover9001, state, 3), vec!['C', 'D', 'E']);
+    }
+
+    #[rstest]
+    fn test_move_onto_same_stack(mut state: State) {
+        CrateMover9000.do_move(&mut state, &Move::new(3, 1, 1));
+        assert_eq!(state.stacks[0].as_slice(), ['A', 'B', 'C', 'D', 'E']);
+    }
+
+    #[rstest]
+    fn test_batch_crane(state: State) {
+        let crane = BatchCrane { capacity: 2 };
+        assert_eq!(moved(&crane, state, 5), vec!['D', 'E', 'B', 'C', 'A']);
+    }
+
+    #[rstest]
+    fn test_batch_crane_matches_models(state: State) {
+        let one = BatchCrane { capacity: 1 };
+        assert_eq!(
+            moved(&one, state.clone(), 4),
+            moved(&CrateMover9000, state.clone(), 4)
+        );
+
+        let big = BatchCrane { capacity: 10 };
+        assert_eq!(
+            moved(&big, state.clone(), 4),
+            moved(&CrateMover9001, state, 4)
+        );
+    }
+
+    #[rstest]
+    fn test_reversing_crane(state: State) {
+        let crane = ReversingCrane { capacity: 2 };
+        assert_eq!(moved(&crane, state.clone(), 4), vec!['D', 'E', 'B', 'C']);
+        assert_eq!(
+            moved(&crane, state.clone(), 5),
+            vec!['D', 'E', 'B', 'C', 'A']
+        );
+
+        let crane = ReversingCrane { capacity: 3 };
+        assert_eq!(moved(&crane, state, 5), vec!['C', 'D', 'E', 'B', 'A']);
+    }
+
+    #[test]
+    fn test_crane_by_name() {
+        assert_eq!(crane_by_name("9000").unwrap().name(), "CrateMover 9000");
+        assert_eq!(crane_by_name("9001").unwrap().name(), "CrateMover 9001");
+        assert_eq!(
+            crane_by_name("batch:3").unwrap().name(),
+            "batch crane (capacity 3)"
+        );
+        assert_eq!(
+            crane_by_name("reversing:2").unwrap().name(),
+            "reversing crane (capacity 2)"
+        );
+        assert!(crane_by_name("9002").is_err());
+        assert!(crane_by_name("batch:0").is_err());
+        assert!(crane_by_name("batch:x").is_err());
+    }
+}
@@ -0,0 +1,238 @@
+// Parsing and rendering the stack drawing:
+//
+//     [D]
+// [N] [C]
+// [Z] [M] [P]
+//  1   2   3
+//
+// Crates are matched to stacks by the columns of the labels on the last line,
+// rather than by assuming a fixed spacing, so labels can be any width.
+
+use crate::State;
+use color_eyre::eyre::{eyre, Report, Result, WrapErr};
+use common::arena::StackArena;
+use common::stack::Stack;
+use std::fmt;
+
+// Each label with the column (in chars) it starts at
+fn parse_labels(line: &str) -> Vec<(usize, String)> {
+    let mut labels = vec![];
+    let mut current: Option<(usize, String)> = None;
+
+    for (col, ch) in line.chars().enumerate() {
+        match (ch.is_whitespace(), current.as_mut()) {
+            (true, Some(_)) => labels.extend(current.take()),
+            (true, None) => {}
+            (false, Some((_, label))) => label.push(ch),
+            (false, None) => current = Some((col, ch.to_string())),
+        }
+    }
+    labels.extend(current);
+
+    labels
+}
+
+// Each "[X]" crate in a row, as the column of X and X itself
+fn parse_crates(line: &str) -> Result<Vec<(usize, char)>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut crates = vec![];
+
+    let mut col = 0;
+    while col < chars.len() {
+        match chars[col..] {
+            [' ', ..] => col += 1,
+            ['[', glyph, ']', ..] if !glyph.is_whitespace() => {
+                crates.push((col + 1, glyph));
+                col += 3;
+            }
+            _ => return Err(eyre!("expected a crate like [X] at column {}", col + 1)),
+        }
+    }
+
+    Ok(crates)
+}
+
+// The stack a crate at `col` belongs to: the label it is above, allowing for
+// the brackets either side.
+fn find_stack(labels: &[(usize, String)], col: usize) -> Option<usize> {
+    labels
+        .iter()
+        .position(|(start, label)| col + 1 >= *start && col <= start + label.chars().count())
+}
+
+impl TryFrom<Vec<String>> for State {
+    type Error = Report;
+
+    fn try_from(drawing: Vec<String>) -> Result<Self, Self::Error> {
+        let (label_line, rows) = drawing.split_last().ok_or(eyre!("no labels"))?;
+        let labels = parse_labels(label_line);
+        if labels.is_empty() {
+            return Err(eyre!("no stacks"));
+        }
+
+        let mut state = State {
+            stacks: StackArena::new(labels.len()),
+            labels: labels.iter().map(|(_, label)| label.clone()).collect(),
+        };
+
+        // parse the stacks from the bottom up, so `level` is the height
+        // every stack with a crate in this row should already have
+        for (level, (row, line)) in rows.iter().enumerate().rev().enumerate() {
+            let crates = parse_crates(line).wrap_err(format!("line {}", row + 1))?;
+            for (col, glyph) in crates {
+                let i = find_stack(&labels, col).ok_or(eyre!(
+                    "line {}: crate {glyph} at column {} is not above a label",
+                    row + 1,
+                    col + 1
+                ))?;
+
+                let stack = &mut state.stacks[i];
+                if stack.len() < level {
+                    return Err(eyre!(
+                        "line {}: crate {glyph} in stack {} is floating",
+                        row + 1,
+                        labels[i].1
+                    ));
+                }
+                if stack.len() > level {
+                    return Err(eyre!(
+                        "line {}: more than one crate above stack {}",
+                        row + 1,
+                        labels[i].1
+                    ));
+                }
+                stack.push(glyph);
+            }
+        }
+
+        Ok(state)
+    }
+}
+
+// Draw the stacks in the same format as the puzzle input. Each stack is at
+// least as wide as a crate, or wider if its label doesn't fit.
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .labels
+            .iter()
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(3);
+
+        // centred, any odd space goes on the right
+        let centre = |s: String| {
+            let left = (width - s.chars().count()) / 2;
+            format!("{:left$}{s:<rest$}", "", rest = width - left)
+        };
+
+        let height = self.stacks.iter().map(Stack::len).max().unwrap_or(0);
+
+        for level in (0..height).rev() {
+            let row: Vec<String> = self
+                .stacks
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(item) => centre(format!("[{item}]")),
+                    None => centre(String::new()),
+                })
+                .collect();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+
+        let labels: Vec<String> = self.labels.iter().cloned().map(centre).collect();
+        write!(f, "{}", labels.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn lines(s: &str) -> Vec<String> {
+        s.split('\n').map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_labels() {
+        assert_eq!(
+            parse_labels(" 1   2   3 "),
+            vec![(1, "1".into()), (5, "2".into()), (9, "3".into())]
+        );
+        assert_eq!(
+            parse_labels(" 9  10  11"),
+            vec![(1, "9".into()), (4, "10".into()), (8, "11".into())]
+        );
+        assert_eq!(parse_labels(" A  "), vec![(1, "A".into())]);
+        assert_eq!(parse_labels("   "), vec![]);
+    }
+
+    #[test]
+    fn test_parse_crates() {
+        assert_eq!(
+            parse_crates("[Z] [M] [P]").unwrap(),
+            vec![(1, 'Z'), (5, 'M'), (9, 'P')]
+        );
+        assert_eq!(parse_crates("    [7]    ").unwrap(), vec![(5, '7')]);
+        assert_eq!(parse_crates("[]] [ü]").unwrap(), vec![(1, ']'), (5, 'ü')]);
+        assert!(parse_crates("[Z] [ ]").is_err());
+        assert!(parse_crates("[Z] M").is_err());
+        assert!(parse_crates("[Z").is_err());
+    }
+
+    #[test]
+    fn test_any_glyph() {
+        let state = State::try_from(lines("[a] [#]\n[1] [ü]\n 1   2 ")).unwrap();
+        assert_eq!(
+            state,
+            State::with_stacks(vec![vec!['1', 'a'], vec!['ü', '#']])
+        );
+    }
+
+    #[test]
+    fn test_more_than_nine_stacks() {
+        let drawing = "                                        [K]
+[A] [B] [C] [D] [E] [F] [G] [H] [I] [J] [L]
+ 1   2   3   4   5   6   7   8   9  10  11 ";
+        let state = State::try_from(lines(drawing)).unwrap();
+        assert_eq!(state.stacks.len(), 11);
+        assert_eq!(state.labels[9], "10");
+        assert_eq!(state.stacks[9].as_slice(), ['J']);
+        assert_eq!(state.stacks[10].as_slice(), ['L', 'K']);
+        assert_eq!(state.output(), "ABCDEFGHIJK");
+    }
+
+    #[test_case("    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 " ; "example")]
+    #[test_case("[A]         [J]\n 1   2   9  10 " ; "two digit label")]
+    #[test_case("             [X] \n [Y]   [Z]   [W] \n one   two  three" ; "word labels")]
+    #[test_case(" 1 " ; "no crates")]
+    fn test_round_trip(drawing: &str) {
+        let state = State::try_from(lines(drawing)).unwrap();
+        assert_eq!(state.to_string(), drawing);
+    }
+
+    #[test]
+    fn test_bad_drawing() {
+        let err = |s: &str| format!("{:#}", State::try_from(lines(s)).unwrap_err());
+
+        assert_eq!(err(""), "no stacks");
+        assert_eq!(
+            err("[A]\n    \n 1 "),
+            "line 1: crate A in stack 1 is floating"
+        );
+        assert_eq!(
+            err("        [A]\n 1   2 "),
+            "line 1: crate A at column 10 is not above a label"
+        );
+        assert_eq!(
+            err("[A][B]\nthree"),
+            "line 1: more than one crate above stack three"
+        );
+        assert_eq!(
+            err("[A] B\n 1 "),
+            "line 1: expected a crate like [X] at column 5"
+        );
+    }
+}
@@ -1,13 +1,54 @@
+mod crane;
+mod drawing;
+mod simulator;
+mod validate;
+
+use crate::crane::{crane_by_name, Crane};
+use crate::simulator::{interact, Simulator};
+use crate::validate::MoveError;
 use color_eyre::eyre::{eyre, Report, Result};
-use common::select_and_solve;
+use common::arena::StackArena;
+use common::parse::parse_complete;
+use common::parsers::{crane_move, CraneMove};
+#[cfg(test)]
+use common::stack::Stack;
+use common::{load_input, select_and_solve};
+use std::{env, fmt, io};
 
 fn main() -> Result<()> {
     color_eyre::install()?;
-    select_and_solve("inputs/day05.1", part1, "inputs/day05.2", part2)?;
+
+    let args: Vec<String> = env::args().collect();
+
+    // day05 sim [crane] [filename]
+    if args.get(1).map(String::as_str) == Some("sim") {
+        let crane = crane_by_name(args.get(2).map_or("9000", String::as_str))?;
+        let filename = args.get(3).map_or("inputs/day05.1", String::as_str);
+        return simulate(load_input(filename)?, crane);
+    }
+
+    // day05 <part> [crane]
+    let crane = args.get(2).map(String::as_str);
+    select_and_solve(
+        "inputs/day05.1",
+        |input| part1(input, crane),
+        "inputs/day05.2",
+        |input| part2(input, crane),
+    )?;
     Ok(())
 }
 
-fn part1(input: Vec<String>) -> Result<String> {
+fn simulate(input: Vec<String>, crane: Box<dyn Crane>) -> Result<()> {
+    let (drawing, moves) = split_input(input);
+    let state = State::try_from(drawing)?;
+    let moves = parse_moves(&moves)?;
+
+    let mut sim = Simulator::new(state, moves, crane)?;
+    interact(&mut sim, io::stdin().lock(), io::stdout())
+}
+
+fn part1(input: Vec<String>, crane: Option<&str>) -> Result<String> {
+    let crane = crane_by_name(crane.unwrap_or("9000"))?;
     let (drawing, moves) = split_input(input);
 
     for line in &drawing {
@@ -18,171 +59,157 @@ fn part1(input: Vec<String>) -> Result<String> {
 
     println!("{:?}", state);
 
-    let moves: Vec<Move> = moves
-        .iter()
-        .map(|s| Move::try_from(s.as_str()))
-        .collect::<Result<Vec<Move>>>()?;
+    let moves = parse_moves(&moves)?;
 
-    state.do_moves(moves);
+    crane.do_moves(&mut state, &moves)?;
 
-    println!("{:?}", state);
+    println!("{}", state);
 
     Ok(state.output())
 }
 
-fn part2(input: Vec<String>) -> Result<String> {
+fn part2(input: Vec<String>, crane: Option<&str>) -> Result<String> {
+    let crane = crane_by_name(crane.unwrap_or("9001"))?;
     let (drawing, moves) = split_input(input);
     let mut state = State::try_from(drawing)?;
-    let moves: Vec<Move> = moves
-        .iter()
-        .map(|s| Move::try_from(s.as_str()))
-        .collect::<Result<Vec<Move>>>()?;
+    let moves = parse_moves(&moves)?;
 
-    state.do_moves_9001(moves);
+    crane.do_moves(&mut state, &moves)?;
     Ok(state.output())
 }
 
 fn split_input(mut input: Vec<String>) -> (Vec<String>, Vec<String>) {
+    // A drawing without any move lines is a valid input with zero moves
     let index = input
         .iter()
-        .enumerate()
-        .find(|&r| r.1.starts_with("move"))
-        .expect("input has move lines")
-        .0;
+        .position(|line| line.starts_with("move"))
+        .unwrap_or(input.len());
 
     let moves = input.split_off(index);
 
-    // Remove the last (blank) line of the diagram
-    input.pop();
+    // Remove the blank line(s) separating the diagram from the moves
+    while input.last().is_some_and(|line| line.trim().is_empty()) {
+        input.pop();
+    }
 
     (input, moves)
 }
 
-fn get_num_stacks(s: &str) -> Option<u32> {
-    let last_char = s.trim().chars().last()?;
-    last_char.to_digit(10)
-}
-
-fn stack_offset(i: usize) -> usize {
-    // "[x] [x] [x] ..."
-    1 + i * 4
+fn parse_moves(moves: &[String]) -> Result<Vec<Move>, MoveError> {
+    moves
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            Move::try_from(line.as_str()).map_err(|e| MoveError::Parse {
+                index,
+                line: line.clone(),
+                reason: e.to_string(),
+            })
+        })
+        .collect()
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 struct State {
-    stacks: Vec<Vec<char>>,
+    stacks: StackArena<char>,
+    labels: Vec<String>, // as drawn, moves refer to stacks by position
 }
 
 impl State {
-    fn new(num_stacks: u32) -> Self {
+    // Stacks labelled 1, 2, 3, ...
+    #[cfg(test)]
+    fn with_stacks(stacks: Vec<Vec<char>>) -> Self {
+        let labels = (1..=stacks.len()).map(|i| i.to_string()).collect();
         State {
-            stacks: vec![vec![]; num_stacks as usize],
-        }
-    }
-
-    fn add_to_stacks(&mut self, line: &str) -> Result<()> {
-        // parse a line and add to stacks
-        let num_stacks = self.stacks.len();
-        for i in 0..num_stacks {
-            let p = stack_offset(i);
-            let item = line.chars().nth(p).ok_or(eyre!("line too short"))?;
-            if item.is_ascii_uppercase() {
-                self.stacks[i].push(item);
-            }
+            stacks: stacks.into_iter().map(Stack::from).collect(),
+            labels,
         }
-        Ok(())
     }
 
-    fn do_moves(&mut self, moves: Vec<Move>) {
-        for mv in moves {
-            self.do_move(mv);
-        }
-    }
-
-    fn do_move(&mut self, mv: Move) {
-        for _ in 0..mv.count {
-            let item = self.stacks[mv.src as usize]
-                .pop()
-                .expect("stack is not empty");
-            self.stacks[mv.dst as usize].push(item);
-        }
+    // Move crates between stacks, either all at once or one at a time (see
+    // validate::check_move for the conditions this relies on)
+    fn move_crates(&mut self, mv: &Move, count: u32, preserve_order: bool) {
+        self.stacks
+            .move_n(
+                mv.src as usize,
+                mv.dst as usize,
+                count as usize,
+                preserve_order,
+            )
+            .expect("move was checked");
     }
 
-    // Part 2
-    fn do_moves_9001(&mut self, moves: Vec<Move>) {
-        for mv in moves {
-            self.do_move_9001(mv);
-        }
-    }
-
-    fn do_move_9001(&mut self, mv: Move) {
-        let src_stack = &mut self.stacks[mv.src as usize];
-        let new_len = src_stack.len() - mv.count as usize;
-        let moved_items: Vec<char> = src_stack.drain(new_len..).collect();
-
-        let dst_stack = &mut self.stacks[mv.dst as usize];
-        // for i in moved_items {
-        //     dst_stack.push(i);
-        // }
-        dst_stack.extend(moved_items);
+    fn heights(&self) -> Vec<usize> {
+        self.stacks.heights()
     }
 
+    // The top item of each stack, or a space if the stack is empty
     fn output(&self) -> String {
-        let value: String = self
-            .stacks
-            .iter()
-            .map(|stack| stack.last().expect("valid item"))
-            .collect();
-        value
+        self.stacks
+            .tops()
+            .map(|top| top.copied().unwrap_or(' '))
+            .collect()
     }
 }
 
-impl TryFrom<Vec<String>> for State {
-    type Error = Report;
-
-    fn try_from(drawing: Vec<String>) -> Result<Self, Self::Error> {
-        let stack_labels = drawing.last().ok_or(eyre!("no labels"))?;
-        let num_stacks = get_num_stacks(stack_labels).ok_or(eyre!("no stacks"))?;
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Move {
+    count: u32,
+    src: u32, // zero-based
+    dst: u32, // zero-based
+}
 
-        // parse the stacks from the bottom up, skipping the label line:
-        let mut state = State::new(num_stacks);
-        let mut ri = drawing.iter().rev();
-        ri.next();
-        for line in ri {
-            state.add_to_stacks(line)?;
+impl Move {
+    // src and dst are one-based, as written in the input
+    #[cfg(test)]
+    fn new(count: u32, src: u32, dst: u32) -> Self {
+        Move {
+            count,
+            src: src - 1,
+            dst: dst - 1,
         }
-        Ok(state)
     }
 }
 
-#[derive(Debug)]
-struct Move {
-    count: u32,
-    src: u32, // zero-based
-    dst: u32, // zero-based
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.count,
+            self.src + 1,
+            self.dst + 1
+        )
+    }
 }
 
 impl TryFrom<&str> for Move {
     type Error = Report;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let parts: Vec<&str> = value.split_whitespace().collect();
-
-        if parts.len() < 4 {
-            return Err(eyre!("bad move"));
-        }
-
-        let count = str::parse::<u32>(parts[1])?;
-        let src = str::parse::<u32>(parts[3])? - 1;
-        let dst = str::parse::<u32>(parts[5])? - 1;
+        let CraneMove { count, from, to } =
+            parse_complete(crane_move, value).map_err(|e| eyre!("{}", e.reason()))?;
+
+        // stacks are numbered from 1 in the input
+        let stack = |n: usize| -> Result<u32> {
+            match n {
+                0 => Err(eyre!("there is no stack 0")),
+                n => Ok(u32::try_from(n - 1)?),
+            }
+        };
 
-        Ok(Move { count, src, dst })
+        Ok(Move {
+            count: u32::try_from(count)?,
+            src: stack(from)?,
+            dst: stack(to)?,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::vec_of_strings;
     use rstest::*;
 
     #[fixture]
@@ -203,28 +230,20 @@ move 1 from 1 to 2"
 
     #[rstest]
     fn test_part1(input: Vec<String>) {
-        assert_eq!(part1(input).unwrap(), "CMZ");
+        assert_eq!(part1(input, None).unwrap(), "CMZ");
     }
 
     #[rstest]
     fn test_part2(input: Vec<String>) {
-        assert_eq!(part2(input).unwrap(), "MCD");
+        assert_eq!(part2(input, None).unwrap(), "MCD");
     }
 
-    #[test]
-    fn test_stack_offset() {
-        assert_eq!(stack_offset(0), 1);
-        assert_eq!(stack_offset(1), 5);
-    }
-
-    #[test]
-    fn test_get_num_stacks() {
-        assert_eq!(get_num_stacks(" 1   2   3 "), Some(3));
-        assert_eq!(
-            get_num_stacks(" 1   2   3   4   5   6   7   8   9 "),
-            Some(9)
-        );
-        assert_eq!(get_num_stacks(" 1   2   3   4   5   6   7   8   A "), None);
+    #[rstest]
+    fn test_select_crane(input: Vec<String>) {
+        assert_eq!(part1(input.clone(), Some("9001")).unwrap(), "MCD");
+        assert_eq!(part2(input.clone(), Some("9000")).unwrap(), "CMZ");
+        assert_eq!(part1(input.clone(), Some("batch:1")).unwrap(), "CMZ");
+        assert!(part1(input, Some("crane")).is_err());
     }
 
     #[test]
@@ -238,8 +257,86 @@ move 1 from 1 to 2"
             .collect();
         let state = State::try_from(drawing).expect("");
         assert_eq!(state.stacks.len(), 3);
-        assert_eq!(state.stacks[0], vec!['Z', 'N']);
-        assert_eq!(state.stacks[1], vec!['M', 'C', 'D']);
-        assert_eq!(state.stacks[2], vec!['P']);
+        assert_eq!(state.stacks[0].as_slice(), ['Z', 'N']);
+        assert_eq!(state.stacks[1].as_slice(), ['M', 'C', 'D']);
+        assert_eq!(state.stacks[2].as_slice(), ['P']);
+    }
+
+    #[test]
+    fn test_display_state() {
+        let drawing = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 ";
+        let lines: Vec<String> = drawing.split('\n').map(|s| s.to_string()).collect();
+        let state = State::try_from(lines).unwrap();
+        assert_eq!(state.to_string(), drawing);
+    }
+
+    #[test]
+    fn test_parse_moves() {
+        let moves = vec_of_strings!["move 1 from 2 to 1", "move 3 from 1 to 3"];
+        assert_eq!(
+            parse_moves(&moves).unwrap(),
+            vec![Move::new(1, 2, 1), Move::new(3, 1, 3)]
+        );
+
+        let bad = |line: &str| match parse_moves(&vec_of_strings!["move 1 from 2 to 1", line]) {
+            Err(MoveError::Parse { index, reason, .. }) => (index, reason),
+            other => panic!("unexpected {other:?}"),
+        };
+        assert_eq!(bad("move 1 from 2"), (1, "expected \" to \"".to_owned()));
+        assert_eq!(
+            bad("move 1 from 2 onto 3"),
+            (1, "expected \" to \"".to_owned())
+        );
+        assert_eq!(
+            bad("mvoe 1 from 2 to 3"),
+            (1, "expected \"move \"".to_owned())
+        );
+        assert_eq!(
+            bad("move 1 from 0 to 3"),
+            (1, "there is no stack 0".to_owned())
+        );
+        assert_eq!(
+            bad("move -1 from 2 to 3"),
+            (1, "invalid digit found in string".to_owned())
+        );
+    }
+
+    #[rstest]
+    fn test_impossible_move(mut input: Vec<String>) {
+        input[6] = "move 4 from 1 to 3".to_owned();
+        let err = part2(input, None).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MoveError>(),
+            Some(&MoveError::NotEnoughCrates {
+                index: 1,
+                stack: 1,
+                requested: 4,
+                available: 3
+            })
+        );
+    }
+
+    #[rstest]
+    fn test_no_moves(mut input: Vec<String>) {
+        input.truncate(4);
+        assert_eq!(part1(input.clone(), None).unwrap(), "NDP");
+        input.push(String::new());
+        assert_eq!(part2(input, None).unwrap(), "NDP");
+    }
+
+    #[test]
+    fn test_output_empty_stack() {
+        let state = State::with_stacks(vec![vec!['A'], vec![], vec!['B', 'C']]);
+        assert_eq!(state.output(), "A C");
+    }
+
+    #[test]
+    fn test_display_move() {
+        let mv = Move::try_from("move 3 from 1 to 9").unwrap();
+        assert_eq!(mv, Move::new(3, 1, 9));
+        assert_eq!(mv.to_string(), "move 3 from 1 to 9");
     }
 }
@@ -0,0 +1,260 @@
+// Step through a move list one move at a time, recording every intermediate
+// state so that we can go back as well as forward.
+
+use crate::crane::Crane;
+use crate::validate::{validate_moves, MoveError};
+use crate::{Move, State};
+use color_eyre::eyre::Result;
+use std::io::{BufRead, Write};
+
+pub struct Simulator {
+    crane: Box<dyn Crane>,
+    moves: Vec<Move>,
+
+    // history[i] is the state after the first i moves, so history[0] is the
+    // initial state. Only grows - stepping back just moves the position.
+    history: Vec<State>,
+    position: usize,
+}
+
+impl Simulator {
+    // The whole move list is validated up front, so stepping can't fail
+    pub fn new(initial: State, moves: Vec<Move>, crane: Box<dyn Crane>) -> Result<Self, MoveError> {
+        validate_moves(&initial, &moves)?;
+        Ok(Self {
+            crane,
+            moves,
+            history: vec![initial],
+            position: 0,
+        })
+    }
+
+    pub fn current(&self) -> &State {
+        &self.history[self.position]
+    }
+
+    // Number of moves applied to reach the current state
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn num_moves(&self) -> usize {
+        self.moves.len()
+    }
+
+    // The move that will be applied by the next step_forward(), if any
+    pub fn next_move(&self) -> Option<&Move> {
+        self.moves.get(self.position)
+    }
+
+    // The move that produced the current state, if any
+    pub fn last_move(&self) -> Option<&Move> {
+        self.position.checked_sub(1).and_then(|i| self.moves.get(i))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position == self.moves.len()
+    }
+
+    pub fn step_forward(&mut self) -> Option<&State> {
+        let mv = *self.moves.get(self.position)?;
+
+        // only compute states we haven't seen before
+        if self.position + 1 == self.history.len() {
+            let mut next = self.current().clone();
+            self.crane.do_move(&mut next, &mv);
+            self.history.push(next);
+        }
+
+        self.position += 1;
+        Some(self.current())
+    }
+
+    pub fn step_back(&mut self) -> Option<&State> {
+        self.position = self.position.checked_sub(1)?;
+        Some(self.current())
+    }
+
+    pub fn rewind(&mut self) {
+        self.position = 0;
+    }
+
+    pub fn run(&mut self) -> &State {
+        while self.step_forward().is_some() {}
+        self.current()
+    }
+
+    // Every recorded state so far, paired with the move that produced it
+    // (None for the initial state).
+    pub fn replay(&self) -> impl Iterator<Item = (Option<&Move>, &State)> {
+        self.history
+            .iter()
+            .enumerate()
+            .map(|(i, state)| (i.checked_sub(1).map(|j| &self.moves[j]), state))
+    }
+}
+
+// Drive a simulator from line-based commands:
+//   n (or empty line) - next move
+//   p                 - previous move
+//   r                 - rewind to the start
+//   e                 - run to the end
+//   h                 - list the moves recorded so far
+//   q                 - quit
+// The stacks are drawn after every command.
+pub fn interact(sim: &mut Simulator, input: impl BufRead, mut output: impl Write) -> Result<()> {
+    writeln!(output, "{}, {} moves", sim.crane.name(), sim.num_moves())?;
+    show(sim, &mut output)?;
+
+    for line in input.lines() {
+        match line?.trim() {
+            "" | "n" => {
+                if sim.is_finished() {
+                    writeln!(output, "at end")?;
+                }
+                sim.step_forward();
+            }
+            "p" => {
+                if sim.step_back().is_none() {
+                    writeln!(output, "at start")?;
+                }
+            }
+            "r" => sim.rewind(),
+            "e" => {
+                sim.run();
+            }
+            "h" => {
+                for (i, (mv, _)) in sim.replay().enumerate() {
+                    let marker = if i == sim.position() { '>' } else { ' ' };
+                    match mv {
+                        Some(mv) => writeln!(output, "{marker} {i}: {mv}")?,
+                        None => writeln!(output, "{marker} {i}: start")?,
+                    }
+                }
+                continue;
+            }
+            "q" => break,
+            other => {
+                writeln!(output, "unknown command: {other}")?;
+                continue;
+            }
+        }
+        show(sim, &mut output)?;
+    }
+
+    Ok(())
+}
+
+fn show(sim: &Simulator, output: &mut impl Write) -> Result<()> {
+    match sim.last_move() {
+        Some(mv) => writeln!(output, "[{}/{}] {mv}", sim.position(), sim.num_moves())?,
+        None => writeln!(output, "[0/{}] start", sim.num_moves())?,
+    }
+    writeln!(output, "{}", sim.current())?;
+    if let Some(mv) = sim.next_move() {
+        writeln!(output, "next: {mv}")?;
+    }
+    writeln!(output)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crane::{CrateMover9000, CrateMover9001};
+    use rstest::*;
+
+    #[fixture]
+    fn sim() -> Simulator {
+        let drawing: Vec<String> = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 "
+            .split('\n')
+            .map(|s| s.to_string())
+            .collect();
+        let moves = vec![
+            Move::new(1, 2, 1),
+            Move::new(3, 1, 3),
+            Move::new(2, 2, 1),
+            Move::new(1, 1, 2),
+        ];
+        Simulator::new(
+            State::try_from(drawing).unwrap(),
+            moves,
+            Box::new(CrateMover9000),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        let state = State::with_stacks(vec![vec!['A'], vec![]]);
+        let moves = vec![Move::new(1, 1, 2), Move::new(1, 1, 2)];
+        assert!(matches!(
+            Simulator::new(state, moves, Box::new(CrateMover9000)),
+            Err(MoveError::NotEnoughCrates { index: 1, .. })
+        ));
+    }
+
+    #[rstest]
+    fn test_step_forward_and_back(mut sim: Simulator) {
+        let initial = sim.current().clone();
+
+        let after_one = sim.step_forward().unwrap().clone();
+        assert_eq!(after_one.stacks[0].as_slice(), ['Z', 'N', 'D']);
+        assert_eq!(sim.position(), 1);
+
+        sim.step_forward();
+        assert_eq!(sim.step_back(), Some(&after_one));
+        assert_eq!(sim.step_back(), Some(&initial));
+        assert_eq!(sim.step_back(), None);
+        assert_eq!(sim.position(), 0);
+    }
+
+    #[rstest]
+    fn test_run(mut sim: Simulator) {
+        assert_eq!(sim.run().output(), "CMZ");
+        assert!(sim.is_finished());
+        assert_eq!(sim.step_forward(), None);
+        assert_eq!(sim.replay().count(), 5);
+    }
+
+    #[rstest]
+    fn test_run_9001(mut sim: Simulator) {
+        sim.crane = Box::new(CrateMover9001);
+        assert_eq!(sim.run().output(), "MCD");
+    }
+
+    #[rstest]
+    fn test_replay_after_rewind(mut sim: Simulator) {
+        sim.run();
+        let recorded: Vec<State> = sim.replay().map(|(_, s)| s.clone()).collect();
+
+        sim.rewind();
+        for expected in &recorded[1..] {
+            assert_eq!(sim.step_forward(), Some(expected));
+        }
+    }
+
+    #[rstest]
+    fn test_interact(mut sim: Simulator) {
+        let mut output = vec![];
+        interact(&mut sim, "n\np\nn\n\nh\nq\nn\n".as_bytes(), &mut output).unwrap();
+        assert_eq!(sim.position(), 2);
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("CrateMover 9000, 4 moves\n[0/4] start\n"));
+        assert!(output.contains(
+            "[2/4] move 3 from 1 to 3
+        [Z]
+        [N]
+    [C] [D]
+    [M] [P]
+ 1   2   3 
+next: move 2 from 2 to 1
+"
+        ));
+        assert!(output.ends_with("  0: start\n  1: move 1 from 2 to 1\n> 2: move 3 from 1 to 3\n"));
+    }
+}
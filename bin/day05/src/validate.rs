@@ -0,0 +1,145 @@
+// Checking moves against the stacks before they are carried out, so that a bad
+// move file is reported rather than panicking halfway through.
+
+use crate::{Move, State};
+
+// Move indices are zero-based positions in the move list, but are reported
+// counting from 1, like the stack numbers, as written in the input.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum MoveError {
+    #[error("move {}: cannot parse {line:?}: {reason}", .index + 1)]
+    Parse {
+        index: usize,
+        line: String,
+        reason: String,
+    },
+
+    #[error(
+        "move {}: there is no stack {stack}, only {num_stacks} stacks",
+        .index + 1
+    )]
+    NoSuchStack {
+        index: usize,
+        stack: u32,
+        num_stacks: usize,
+    },
+
+    #[error(
+        "move {}: {requested} crates requested from stack {stack}, only {available} available",
+        .index + 1
+    )]
+    NotEnoughCrates {
+        index: usize,
+        stack: u32,
+        requested: u32,
+        available: usize,
+    },
+}
+
+// Check that a single move can be carried out, given the height of each stack.
+pub fn check_move(index: usize, mv: &Move, heights: &[usize]) -> Result<(), MoveError> {
+    for stack in [mv.src, mv.dst] {
+        if stack as usize >= heights.len() {
+            return Err(MoveError::NoSuchStack {
+                index,
+                stack: stack + 1,
+                num_stacks: heights.len(),
+            });
+        }
+    }
+
+    let available = heights[mv.src as usize];
+    if (mv.count as usize) > available {
+        return Err(MoveError::NotEnoughCrates {
+            index,
+            stack: mv.src + 1,
+            requested: mv.count,
+            available,
+        });
+    }
+
+    Ok(())
+}
+
+// Dry run: check a whole move list without moving any crates. Every crane
+// model moves the same number of crates, so only the stack heights matter.
+pub fn validate_moves(state: &State, moves: &[Move]) -> Result<(), MoveError> {
+    let mut heights = state.heights();
+    for (index, mv) in moves.iter().enumerate() {
+        check_move(index, mv, &heights)?;
+        heights[mv.src as usize] -= mv.count as usize;
+        heights[mv.dst as usize] += mv.count as usize;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[fixture]
+    fn state() -> State {
+        State::with_stacks(vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']])
+    }
+
+    #[rstest]
+    fn test_validate_moves(state: State) {
+        let moves = vec![
+            Move::new(1, 2, 1),
+            Move::new(3, 1, 3),
+            Move::new(2, 2, 1),
+            Move::new(1, 1, 2),
+        ];
+        assert_eq!(validate_moves(&state, &moves), Ok(()));
+    }
+
+    #[rstest]
+    fn test_validate_not_enough_crates(state: State) {
+        let moves = vec![Move::new(1, 2, 1), Move::new(4, 1, 3)];
+        assert_eq!(
+            validate_moves(&state, &moves),
+            Err(MoveError::NotEnoughCrates {
+                index: 1,
+                stack: 1,
+                requested: 4,
+                available: 3
+            })
+        );
+    }
+
+    #[rstest]
+    fn test_validate_no_such_stack(state: State) {
+        let moves = vec![Move::new(1, 2, 4)];
+        assert_eq!(
+            validate_moves(&state, &moves),
+            Err(MoveError::NoSuchStack {
+                index: 0,
+                stack: 4,
+                num_stacks: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_message() {
+        let e = MoveError::NotEnoughCrates {
+            index: 7,
+            stack: 2,
+            requested: 5,
+            available: 1,
+        };
+        assert_eq!(
+            e.to_string(),
+            "move 8: 5 crates requested from stack 2, only 1 available"
+        );
+
+        // the first move is move 1
+        let e = MoveError::NoSuchStack {
+            index: 0,
+            stack: 4,
+            num_stacks: 3,
+        };
+        assert_eq!(e.to_string(), "move 1: there is no stack 4, only 3 stacks");
+    }
+}
@@ -1,15 +1,44 @@
 #![allow(dead_code)]
 
-use color_eyre::eyre::{eyre, Result};
+mod stream;
+
+use color_eyre::eyre::{eyre, Result, WrapErr};
 use common::select_and_solve;
+use common::window::{ArrayCounter, BitmaskWindow, CountingWindow, Letter};
 use std::collections::{HashSet, VecDeque};
+use std::env;
+use std::fs::File;
+use std::num::NonZeroUsize;
 
 fn main() -> Result<()> {
     color_eyre::install()?;
+
+    // day06 stream <length> [filename]
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("stream") {
+        let length = args.get(2).ok_or(eyre!("Specify marker length"))?;
+        let filename = args.get(3).map_or("inputs/day06.1", String::as_str);
+        return stream_markers(parse_length(length)?, filename);
+    }
+
     select_and_solve("inputs/day06.1", part1, "inputs/day06.2", part2)?;
     Ok(())
 }
 
+// A marker is at least one character long
+fn parse_length(s: &str) -> Result<NonZeroUsize> {
+    s.parse().map_err(|_| eyre!("Invalid marker length {s}"))
+}
+
+// Print every marker position without loading the whole signal
+fn stream_markers(length: NonZeroUsize, filename: &str) -> Result<()> {
+    let file = File::open(filename).wrap_err(format!("opening {filename}"))?;
+    for position in stream::markers(length, file) {
+        println!("{}", position?);
+    }
+    Ok(())
+}
+
 fn part1(input: Vec<String>) -> Result<String> {
     // expect only one line
     let line = input.first().ok_or(eyre!("no line"))?;
@@ -73,48 +102,28 @@ fn find_marker_alt(length: usize, s: &str) -> Option<usize> {
 
 // Another implementation from
 // https://fasterthanli.me/series/advent-of-code-2022/part-6
-struct State {
-    data: [u8; 256],
-}
-
-impl Default for State {
-    fn default() -> Self {
-        Self { data: [0; 256] }
-    }
-}
-
-impl State {
-    fn push(&mut self, c: u8) {
-        self.data[c as usize] = self.data[c as usize].checked_add(1).unwrap();
-    }
-
-    fn pop(&mut self, c: u8) {
-        self.data[c as usize] = self.data[c as usize].checked_sub(1).unwrap();
-    }
-
-    fn is_unique(&self) -> bool {
-        self.data.iter().all(|&x| x <= 1)
-    }
-}
-
+// counting each byte value as it enters and leaves the window
 fn find_marker_alt2(length: usize, input: &str) -> Option<usize> {
-    assert!(input.len() > length);
+    let mut window = CountingWindow::<u8, ArrayCounter<u8>>::new(length);
 
-    let mut state = State::default();
-    input.bytes().take(length).for_each(|c| state.push(c));
-    if state.is_unique() {
-        return Some(0);
+    for (index, c) in input.bytes().enumerate() {
+        window.push(c);
+        if window.is_full() && window.all_distinct() {
+            return Some(index + 1);
+        }
     }
 
-    for (index, window) in input.as_bytes().windows(length + 1).enumerate() {
-        let removed = window[0];
-        let added = window[length];
+    None
+}
 
-        state.pop(removed);
-        state.push(added);
+// Only works for letters, but needs no counts at all
+fn find_marker_bitmask(length: usize, input: &str) -> Option<usize> {
+    let mut window = BitmaskWindow::new(length);
 
-        if state.is_unique() {
-            return Some(index + 1 + length);
+    for (index, c) in input.bytes().enumerate() {
+        window.push(Letter::try_from(c).ok()?);
+        if window.is_full() && window.all_distinct() {
+            return Some(index + 1);
         }
     }
 
@@ -143,4 +152,33 @@ mod tests {
     fn test_find_som_marker(index: usize, input: &str) {
         assert_eq!(Some(index), find_som_marker(input));
     }
+
+    #[test]
+    fn test_parse_length() {
+        assert_eq!(parse_length("14").unwrap().get(), 14);
+        assert_eq!(
+            parse_length("0").unwrap_err().to_string(),
+            "Invalid marker length 0"
+        );
+        assert!(parse_length("-1").is_err());
+    }
+
+    #[test_case(4, "mjqjpqmgbljsphdztnvjfqwrcgsmlb")]
+    #[test_case(4, "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg")]
+    #[test_case(14, "bvwbjplbgvbhsrlpgdmjqwftvncz")]
+    #[test_case(14, "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw")]
+    fn test_implementations_agree(length: usize, input: &str) {
+        let expected = find_marker(length, input);
+        assert!(expected.is_some());
+        assert_eq!(find_marker_alt(length, input), expected);
+        assert_eq!(find_marker_alt2(length, input), expected);
+        assert_eq!(find_marker_bitmask(length, input), expected);
+    }
+
+    #[test]
+    fn test_short_input() {
+        assert_eq!(find_marker_alt2(4, "abc"), None);
+        assert_eq!(find_marker_bitmask(4, "abc"), None);
+        assert_eq!(find_marker_bitmask(2, "a1"), None);
+    }
 }
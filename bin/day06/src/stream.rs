@@ -0,0 +1,236 @@
+// Marker detection for signals that are too large to hold in memory.
+//
+// MarkerDetector is fed bytes as they arrive, in chunks of any size, and
+// reports every position at which the last `length` characters are all
+// different. It doesn't care where the bytes come from, so it can be driven
+// by an async stream as easily as by markers(), which reads from io::Read.
+//
+// Positions count characters, not bytes, so multibyte UTF-8 characters are
+// handled correctly even if they are split across chunks. A marker is at
+// least one character long, which the length's type makes sure of.
+
+use common::window::CountingWindow;
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read};
+use std::num::NonZeroUsize;
+
+pub struct MarkerDetector {
+    window: CountingWindow<char>,
+    position: usize,  // number of characters received
+    pending: Vec<u8>, // start of a UTF-8 sequence split across chunks
+}
+
+impl MarkerDetector {
+    pub fn new(length: NonZeroUsize) -> Self {
+        Self {
+            window: CountingWindow::new(length.get()),
+            position: 0,
+            pending: vec![],
+        }
+    }
+
+    // Returns the position if this character completes a marker
+    pub fn push_char(&mut self, ch: char) -> Option<usize> {
+        self.window.push(ch);
+        self.position += 1;
+
+        (self.window.is_full() && self.window.all_distinct()).then_some(self.position)
+    }
+
+    // Returns the positions of any markers completed by these bytes
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> io::Result<Vec<usize>> {
+        self.pending.extend_from_slice(bytes);
+        let buffer = std::mem::take(&mut self.pending);
+
+        let (valid, rest) = match std::str::from_utf8(&buffer) {
+            Ok(s) => (s, &[][..]),
+            Err(e) => {
+                if e.error_len().is_some() {
+                    return Err(io::Error::new(ErrorKind::InvalidData, e));
+                }
+                // the last character is incomplete, keep it for next time
+                let (valid, rest) = buffer.split_at(e.valid_up_to());
+                (std::str::from_utf8(valid).expect("valid"), rest)
+            }
+        };
+
+        let markers = valid.chars().filter_map(|ch| self.push_char(ch)).collect();
+        self.pending = rest.to_vec();
+        Ok(markers)
+    }
+
+    // Call at the end of the signal to check it didn't stop mid-character
+    pub fn finish(&self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "signal ends part way through a UTF-8 character",
+            ))
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+pub struct Markers<R> {
+    reader: R,
+    detector: MarkerDetector,
+    found: VecDeque<usize>,
+    done: bool,
+}
+
+// Every marker position in a signal, reading it in chunks
+pub fn markers<R: Read>(length: NonZeroUsize, reader: R) -> Markers<R> {
+    Markers {
+        reader,
+        detector: MarkerDetector::new(length),
+        found: VecDeque::new(),
+        done: false,
+    }
+}
+
+impl<R: Read> Iterator for Markers<R> {
+    type Item = io::Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buffer = [0; 8192];
+
+        while self.found.is_empty() && !self.done {
+            let n = match self.reader.read(&mut buffer) {
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+
+            let result = if n == 0 {
+                self.done = true;
+                self.detector.finish().map(|_| vec![])
+            } else {
+                self.detector.push_bytes(&buffer[..n])
+            };
+
+            match result {
+                Ok(markers) => self.found.extend(markers),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        self.found.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{find_som_marker, find_sop_marker};
+    use test_case::test_case;
+
+    // A reader that hands out one byte at a time
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn length(n: usize) -> NonZeroUsize {
+        NonZeroUsize::new(n).unwrap()
+    }
+
+    fn all_markers(n: usize, reader: impl Read) -> Vec<usize> {
+        markers(length(n), reader)
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+
+    #[test_case("mjqjpqmgbljsphdztnvjfqwrcgsmlb")]
+    #[test_case("bvwbjplbgvbhsrlpgdmjqwftvncz")]
+    #[test_case("nppdvjthqldpwncqszvftbrmjlhg")]
+    #[test_case("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg")]
+    #[test_case("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw")]
+    fn test_first_marker(input: &str) {
+        let mut sop = markers(length(4), input.as_bytes());
+        assert_eq!(
+            sop.next().unwrap().unwrap(),
+            find_sop_marker(input).unwrap()
+        );
+
+        let mut som = markers(length(14), input.as_bytes());
+        assert_eq!(
+            som.next().unwrap().unwrap(),
+            find_som_marker(input).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_every_marker() {
+        assert_eq!(all_markers(4, "mjqjpqmg".as_bytes()), vec![7, 8]);
+        assert_eq!(all_markers(2, "aabba".as_bytes()), vec![3, 5]);
+        assert_eq!(all_markers(3, "aaa".as_bytes()), vec![]);
+        assert_eq!(all_markers(3, "".as_bytes()), vec![]);
+    }
+
+    #[test]
+    fn test_chunking() {
+        let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+        assert_eq!(
+            all_markers(4, Trickle(input.as_bytes())),
+            all_markers(4, input.as_bytes())
+        );
+    }
+
+    #[test]
+    fn test_utf8() {
+        // positions count characters, and 'é' is not the same as 'e'
+        let input = "ééaéeb";
+        assert_eq!(all_markers(3, input.as_bytes()), vec![5, 6]);
+        assert_eq!(all_markers(3, Trickle(input.as_bytes())), vec![5, 6]);
+        assert_eq!(all_markers(2, Trickle("🦀🦀🎄".as_bytes())), vec![3]);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut m = markers(length(2), &b"ab\xffcd"[..]);
+        assert_eq!(
+            m.next().unwrap().unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        assert!(m.next().is_none());
+
+        // truncated multibyte character
+        let bytes = "ab🦀".as_bytes();
+        let mut m = markers(length(2), &bytes[..bytes.len() - 1]);
+        assert_eq!(m.next().unwrap().unwrap(), 2);
+        assert_eq!(
+            m.next().unwrap().unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_push_bytes() {
+        let mut detector = MarkerDetector::new(length(4));
+        assert_eq!(detector.push_bytes(b"mjqj").unwrap(), vec![]);
+        assert_eq!(detector.push_bytes(b"pqmg").unwrap(), vec![7, 8]);
+        assert_eq!(detector.position(), 8);
+        assert!(detector.finish().is_ok());
+    }
+}
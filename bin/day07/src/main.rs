@@ -1,12 +1,14 @@
 use color_eyre::eyre::{eyre, Result};
+use common::parse::{parse_all, ParseResult, Span};
 use common::select_and_solve;
 
 use camino::Utf8PathBuf;
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_while1};
-use nom::combinator::{all_consuming, map};
+use nom::character::complete::newline;
+use nom::combinator::{cut, map};
+use nom::multi::separated_list1;
 use nom::sequence::{preceded, separated_pair};
-use nom::{Finish, IResult};
 
 use id_tree::{InsertBehavior, Node, Tree};
 
@@ -18,9 +20,8 @@ fn main() -> Result<()> {
 
 fn make_tree(input: Vec<String>) -> Result<Tree<FsEntry>> {
     // Parse the input
-    let lines = input
-        .iter()
-        .map(|l| all_consuming(parse_line)(l).finish().unwrap().1);
+    let terminal = input.join("\n");
+    let lines = parse_all(parse_lines, terminal.trim_end())?;
 
     // Initialise a tree
     let mut tree = Tree::<FsEntry>::new();
@@ -115,10 +116,10 @@ fn part2(input: Vec<String>) -> Result<String> {
 }
 
 // https://fasterthanli.me/series/advent-of-code-2022/part-7#part-1
-fn parse_path(i: &str) -> IResult<&str, Utf8PathBuf> {
+fn parse_path(i: Span) -> ParseResult<Utf8PathBuf> {
     map(
         take_while1(|c: char| "abcdefghijklmnopqrstuvwxyz./".contains(c)),
-        Into::into,
+        |s: Span| (*s.fragment()).into(),
     )(i)
 }
 
@@ -126,14 +127,14 @@ fn parse_path(i: &str) -> IResult<&str, Utf8PathBuf> {
 #[derive(Debug, PartialEq)]
 struct Ls;
 
-fn parse_ls(i: &str) -> IResult<&str, Ls> {
+fn parse_ls(i: Span) -> ParseResult<Ls> {
     map(tag("ls"), |_| Ls)(i)
 }
 
 #[derive(Debug, PartialEq)]
 struct Cd(Utf8PathBuf);
 
-fn parse_cd(i: &str) -> IResult<&str, Cd> {
+fn parse_cd(i: Span) -> ParseResult<Cd> {
     map(preceded(tag("cd "), parse_path), Cd)(i)
 }
 
@@ -155,7 +156,7 @@ impl From<Cd> for Command {
     }
 }
 
-fn parse_command(i: &str) -> IResult<&str, Command> {
+fn parse_command(i: Span) -> ParseResult<Command> {
     let (i, _) = tag("$ ")(i)?;
     alt((map(parse_ls, Into::into), map(parse_cd, Into::into)))(i)
 }
@@ -167,7 +168,7 @@ enum Entry {
     File { size: u64, path: Utf8PathBuf },
 }
 
-fn parse_entry(i: &str) -> IResult<&str, Entry> {
+fn parse_entry(i: Span) -> ParseResult<Entry> {
     alt((
         map(
             separated_pair(nom::character::complete::u64, tag(" "), parse_path),
@@ -184,13 +185,17 @@ enum Line {
     Entry(Entry),
 }
 
-fn parse_line(i: &str) -> IResult<&str, Line> {
+fn parse_line(i: Span) -> ParseResult<Line> {
     alt((
         map(parse_command, Line::Command),
         map(parse_entry, Line::Entry),
     ))(i)
 }
 
+fn parse_lines(i: Span) -> ParseResult<Vec<Line>> {
+    separated_list1(newline, cut(parse_line))(i)
+}
+
 // Use id_tree for tree structure
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -210,6 +215,7 @@ fn total_size(tree: &Tree<FsEntry>, node: &Node<FsEntry>) -> Result<u64> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::parse::parse_prefix;
     use rstest::*;
 
     #[fixture]
@@ -258,39 +264,48 @@ $ ls
     #[test]
     fn test_parse_path() {
         assert_eq!(
-            parse_path("abcde fghij"),
-            Ok((" fghij", Utf8PathBuf::from("abcde")))
+            parse_prefix(parse_path, "abcde fghij").ok(),
+            Some((" fghij", Utf8PathBuf::from("abcde")))
         );
     }
 
     #[test]
     fn test_parse_ls() {
-        assert_eq!(parse_ls("ls abcde"), Ok((" abcde", Ls)));
+        assert_eq!(
+            parse_prefix(parse_ls, "ls abcde").ok(),
+            Some((" abcde", Ls))
+        );
     }
 
     #[test]
     fn test_parse_cd() {
         assert_eq!(
-            parse_cd("cd abc.def"),
-            Ok(("", Cd(Utf8PathBuf::from("abc.def"))))
+            parse_prefix(parse_cd, "cd abc.def").ok(),
+            Some(("", Cd(Utf8PathBuf::from("abc.def"))))
         );
     }
 
     #[test]
     fn test_parse_command() {
         assert_eq!(
-            parse_command("$ cd abc.def"),
-            Ok(("", Command::Cd("abc.def".into())))
+            parse_prefix(parse_command, "$ cd abc.def").ok(),
+            Some(("", Command::Cd("abc.def".into())))
+        );
+        assert_eq!(
+            parse_prefix(parse_command, "$ ls\na\nb").ok(),
+            Some(("\na\nb", Command::Ls))
         );
-        assert_eq!(parse_command("$ ls\na\nb"), Ok(("\na\nb", Command::Ls)));
     }
 
     #[test]
     fn test_parse_entry() {
-        assert_eq!(parse_entry("dir abc"), Ok(("", Entry::Dir("abc".into()))));
         assert_eq!(
-            parse_entry("12345 def"),
-            Ok((
+            parse_prefix(parse_entry, "dir abc").ok(),
+            Some(("", Entry::Dir("abc".into())))
+        );
+        assert_eq!(
+            parse_prefix(parse_entry, "12345 def").ok(),
+            Some((
                 "",
                 Entry::File {
                     size: 12345,
@@ -302,18 +317,21 @@ $ ls
 
     #[test]
     fn test_parse_line() {
-        assert_eq!(parse_line("$ ls"), Ok(("", Line::Command(Command::Ls))));
         assert_eq!(
-            parse_line("$ cd foo"),
-            Ok(("", Line::Command(Command::Cd("foo".into()))))
+            parse_prefix(parse_line, "$ ls").ok(),
+            Some(("", Line::Command(Command::Ls)))
+        );
+        assert_eq!(
+            parse_prefix(parse_line, "$ cd foo").ok(),
+            Some(("", Line::Command(Command::Cd("foo".into()))))
         );
         assert_eq!(
-            parse_line("dir abcdef"),
-            Ok(("", Line::Entry(Entry::Dir("abcdef".into()))))
+            parse_prefix(parse_line, "dir abcdef").ok(),
+            Some(("", Line::Entry(Entry::Dir("abcdef".into()))))
         );
         assert_eq!(
-            parse_line("98765 bar"),
-            Ok((
+            parse_prefix(parse_line, "98765 bar").ok(),
+            Some((
                 "",
                 Line::Entry(Entry::File {
                     size: 98765,
@@ -325,11 +343,16 @@ $ ls
 
     #[rstest]
     fn test_parse_sample_input(input: Vec<String>) {
-        let lines = input
-            .iter()
-            .map(|l| all_consuming(parse_line)(l).finish().unwrap().1);
+        let lines = parse_all(parse_lines, &input.join("\n")).unwrap();
         for line in lines {
             println!("{line:?}");
         }
     }
+
+    #[rstest]
+    fn test_parse_error(mut input: Vec<String>) {
+        input[3] = "14848514 B.txt".to_owned();
+        let e = make_tree(input).unwrap_err().to_string();
+        assert!(e.contains("bad input at line 4, column 10"), "{e}");
+    }
 }
@@ -1,4 +1,6 @@
 use color_eyre::eyre::Result;
+use common::parse::parse_all;
+use common::parsers::{digit, grid};
 use common::select_and_solve;
 use grid::Grid;
 
@@ -14,11 +16,11 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn make_grid(input: Vec<String>) -> Grid<u32> {
-    let num_cols = input[0].len();
-    let sdata = input.join("");
-    let data: Vec<u32> = sdata.chars().map(|c| c as u32 - '0' as u32).collect();
-    Grid::from_vec(data, num_cols)
+fn make_grid(input: Vec<String>) -> Result<Grid<u32>> {
+    let text = input.join("\n");
+    let rows = parse_all(grid(digit), text.trim_end())?;
+    let num_cols = rows[0].len();
+    Ok(Grid::from_vec(rows.concat(), num_cols))
 }
 
 enum Direction {
@@ -89,7 +91,7 @@ fn how_many_visible(heights: Grid<u32>) -> usize {
 }
 
 fn part1(input: Vec<String>) -> Result<String> {
-    let grid = make_grid(input);
+    let grid = make_grid(input)?;
     let num_visible = how_many_visible(grid);
     Ok(num_visible.to_string())
 }
@@ -162,7 +164,7 @@ fn scenic_score(heights: &Grid<u32>, or: usize, oc: usize) -> u32 {
 }
 
 fn part2(input: Vec<String>) -> Result<String> {
-    let heights = make_grid(input);
+    let heights = make_grid(input)?;
     let score = max_scenic_score(&heights);
     Ok(score.to_string())
 }
@@ -170,6 +172,7 @@ fn part2(input: Vec<String>) -> Result<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::vec_of_strings;
     use grid::grid;
     use rstest::*;
 
@@ -208,13 +211,18 @@ mod tests {
         .split('\n')
         .map(|s| s.to_string())
         .collect();
-        let grid = make_grid(input);
+        let grid = make_grid(input).unwrap();
         assert_eq!(grid.size(), (2, 2));
         assert_eq!(grid, grid![[1, 2][3, 4]]);
         assert_eq!(grid.get(0, 0), Some(&1));
         assert_eq!(grid.get(0, 1), Some(&2));
         assert_eq!(grid.get(1, 0), Some(&3));
         assert_eq!(grid.get(1, 1), Some(&4));
+
+        let e = make_grid(vec_of_strings!["12", "3"])
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("row has 1 cells, expected 2"), "{e}");
     }
 
     #[test]
@@ -0,0 +1,290 @@
+// Recording a rope simulation step by step, and drawing it: as text, as one
+// SVG per frame, or as a single animated SVG.
+//
+// Every frame is drawn within the same bounds, worked out from the whole
+// simulation, so nothing is cut off and the picture doesn't jump about.
+// Knots are labelled H, 1, 2, ..., the places the tail has been are shaded,
+// and the start is marked s.
+
+use crate::rope::Rope;
+use crate::Coord;
+use std::collections::HashSet;
+use svg::node::element::{AnimateTransform, Element, Group, Rectangle, Text};
+use svg::node::Node;
+use svg::Document;
+
+const SIDE: isize = 16; // size of a cell in an SVG
+const START: Coord = Coord(0, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min: Coord,
+    pub max: Coord,
+}
+
+impl Bounds {
+    fn new(at: Coord) -> Self {
+        Self { min: at, max: at }
+    }
+
+    fn include(&mut self, at: &Coord) {
+        self.min = Coord(self.min.0.min(at.0), self.min.1.min(at.1));
+        self.max = Coord(self.max.0.max(at.0), self.max.1.max(at.1));
+    }
+
+    pub fn width(&self) -> isize {
+        self.max.0 - self.min.0 + 1
+    }
+
+    pub fn height(&self) -> isize {
+        self.max.1 - self.min.1 + 1
+    }
+
+    // Position in an SVG, which has y going down
+    fn to_svg(self, at: &Coord) -> (isize, isize) {
+        ((at.0 - self.min.0) * SIDE, (self.max.1 - at.1) * SIDE)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub frames: Vec<Vec<Coord>>, // knot positions, before the first step and after each one
+    pub trail: Vec<(usize, Coord)>, // where the tail has been, with the frame it got there
+    pub bounds: Bounds,
+}
+
+// Step the rope, keeping every frame
+pub fn record(rope: &mut Rope, steps: impl IntoIterator<Item = Coord>) -> Recording {
+    let mut recording = Recording {
+        frames: vec![rope.knots().to_vec()],
+        trail: vec![(0, rope.tail())],
+        bounds: Bounds::new(START),
+    };
+    let mut seen = HashSet::from([rope.tail()]);
+
+    for direction in steps {
+        rope.step(direction);
+        let frame = recording.frames.len();
+        if seen.insert(rope.tail()) {
+            recording.trail.push((frame, rope.tail()));
+        }
+        recording.frames.push(rope.knots().to_vec());
+    }
+
+    for knot in recording.frames.iter().flatten() {
+        recording.bounds.include(knot);
+    }
+    recording
+}
+
+// Makes its parent visible from `begin` onwards. The svg crate has no type
+// for <set>, so it is built by hand.
+fn reveal(begin: String) -> Element {
+    let mut set = Element::new("set");
+    set.assign("attributeName", "visibility");
+    set.assign("to", "visible");
+    set.assign("begin", begin);
+    set.assign("fill", "freeze");
+    set
+}
+
+fn label(knot: usize) -> String {
+    match knot {
+        0 => "H".to_owned(),
+        i => i.to_string(),
+    }
+}
+
+impl Recording {
+    fn trail_at(&self, frame: usize) -> impl Iterator<Item = &Coord> {
+        self.trail
+            .iter()
+            .take_while(move |(since, _)| *since <= frame)
+            .map(|(_, at)| at)
+    }
+
+    // One character per cell. Knots with more than one digit are shown by
+    // their last digit.
+    pub fn to_text(&self, frame: usize) -> String {
+        let knots = &self.frames[frame];
+        let trail: HashSet<&Coord> = self.trail_at(frame).collect();
+        let Bounds { min, max } = self.bounds;
+
+        let mut text = String::new();
+        for y in (min.1..=max.1).rev() {
+            for x in min.0..=max.0 {
+                let at = Coord(x, y);
+                let ch = match knots.iter().position(|knot| *knot == at) {
+                    Some(i) => label(i).chars().last().expect("non-empty label"),
+                    None if at == START => 's',
+                    None if trail.contains(&at) => '#',
+                    None => '.',
+                };
+                text.push(ch);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn document(&self) -> Document {
+        let (width, height) = (self.bounds.width() * SIDE, self.bounds.height() * SIDE);
+        Document::new()
+            .set("viewBox", (0, 0, width, height))
+            .set("font-family", "monospace")
+            .set("font-size", SIDE - 4)
+            .add(
+                Rectangle::new()
+                    .set("width", width)
+                    .set("height", height)
+                    .set("fill", "white"),
+            )
+    }
+
+    fn cell(&self, at: &Coord, fill: &str) -> Rectangle {
+        let (x, y) = self.bounds.to_svg(at);
+        Rectangle::new()
+            .set("x", x)
+            .set("y", y)
+            .set("width", SIDE)
+            .set("height", SIDE)
+            .set("fill", fill.to_owned())
+    }
+
+    fn text(&self, at: &Coord, s: &str, fill: &str) -> Text {
+        let (x, y) = self.bounds.to_svg(at);
+        Text::new()
+            .set("x", x + SIDE / 2)
+            .set("y", y + SIDE / 2)
+            .set("text-anchor", "middle")
+            .set("dominant-baseline", "central")
+            .set("fill", fill.to_owned())
+            .add(svg::node::Text::new(s))
+    }
+
+    fn start(&self) -> Group {
+        Group::new()
+            .add(self.cell(&START, "none").set("stroke", "black"))
+            .add(self.text(&START, "s", "grey"))
+    }
+
+    pub fn frame_svg(&self, frame: usize) -> String {
+        let mut document = self.document();
+        for at in self.trail_at(frame) {
+            document = document.add(self.cell(at, "lightgrey"));
+        }
+        document = document.add(self.start());
+
+        // the head is drawn last, so it is on top of any knots it covers
+        for (i, knot) in self.frames[frame].iter().enumerate().rev() {
+            document = document
+                .add(self.cell(knot, if i == 0 { "crimson" } else { "steelblue" }))
+                .add(self.text(knot, &label(i), "white"));
+        }
+        document.to_string()
+    }
+
+    // All the frames in one SVG, each shown for `frame_seconds`
+    pub fn animated_svg(&self, frame_seconds: f64) -> String {
+        let duration = format!("{}s", self.frames.len() as f64 * frame_seconds);
+        let begin = |frame: usize| format!("{}s", frame as f64 * frame_seconds);
+
+        let mut document = self.document();
+        for (since, at) in &self.trail {
+            let cell = self.cell(at, "lightgrey");
+            document = document.add(if *since == 0 {
+                cell
+            } else {
+                cell.set("visibility", "hidden").add(reveal(begin(*since)))
+            });
+        }
+        document = document.add(self.start());
+
+        // each knot is drawn at the start, and moved by animating its offset
+        let (start_x, start_y) = self.bounds.to_svg(&START);
+        let num_knots = self.frames[0].len();
+        for i in (0..num_knots).rev() {
+            let offsets: Vec<String> = self
+                .frames
+                .iter()
+                .map(|frame| {
+                    let (x, y) = self.bounds.to_svg(&frame[i]);
+                    format!("{} {}", x - start_x, y - start_y)
+                })
+                .collect();
+
+            let knot = Group::new()
+                .add(self.cell(&START, if i == 0 { "crimson" } else { "steelblue" }))
+                .add(self.text(&START, &label(i), "white"))
+                .add(
+                    AnimateTransform::new()
+                        .set("attributeName", "transform")
+                        .set("type", "translate")
+                        .set("values", offsets.join(";"))
+                        .set("dur", duration.clone())
+                        .set("calcMode", "discrete")
+                        .set("fill", "freeze"),
+                );
+            document = document.add(knot);
+        }
+        document.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rope::Rope;
+
+    const UP: Coord = Coord(0, 1);
+    const LEFT: Coord = Coord(-1, 0);
+
+    fn recording() -> Recording {
+        let mut rope = Rope::new(3);
+        record(&mut rope, [UP, UP, UP, LEFT])
+    }
+
+    #[test]
+    fn test_record() {
+        let recording = recording();
+        assert_eq!(recording.frames.len(), 5);
+        assert_eq!(recording.frames[2], vec![Coord(0, 2), Coord(0, 1), START]);
+        assert_eq!(recording.trail, vec![(0, START), (3, Coord(0, 1))]);
+        assert_eq!(
+            recording.bounds,
+            Bounds {
+                min: Coord(-1, 0),
+                max: Coord(0, 3)
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_text() {
+        let recording = recording();
+        assert_eq!(recording.to_text(0), "..\n..\n..\n.H\n");
+        assert_eq!(recording.to_text(3), ".H\n.1\n.2\n.s\n");
+        assert_eq!(recording.to_text(4), "H.\n.1\n.2\n.s\n");
+    }
+
+    #[test]
+    fn test_frame_svg() {
+        let svg = recording().frame_svg(4);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(r#"viewBox="0 0 32 64""#));
+        // knots, trail and start
+        for label in ["\nH\n", "\n1\n", "\n2\n", "\ns\n"] {
+            assert!(svg.contains(label), "{label} in {svg}");
+        }
+        assert_eq!(svg.matches("lightgrey").count(), 2);
+    }
+
+    #[test]
+    fn test_animated_svg() {
+        let svg = recording().animated_svg(0.5);
+        assert_eq!(svg.matches("<animateTransform").count(), 3);
+        assert!(svg.contains(r#"dur="2.5s""#));
+        // the tail reaches (0, 1) in frame 3
+        assert!(svg.contains(r#"begin="1.5s""#));
+    }
+}
@@ -1,15 +1,48 @@
+mod frames;
+mod rope;
+
+use crate::frames::record;
+use crate::rope::{FollowRule, Rope};
 use color_eyre::eyre::{eyre, Result};
-use common::select_and_solve;
+use common::parse::{parse_all, ParseResult, Span};
+use common::parsers::{direction_count, lines};
+use common::{load_input, select_and_solve};
 use derive_more::{Add, Sub};
-use nom::character::complete::{digit1, multispace0};
-use nom::combinator::map_res;
-use nom::sequence::tuple;
-use nom::IResult;
-use std::collections::HashSet;
+use nom::character::complete::space0;
+use std::env;
+use std::fs;
+use std::path::Path;
 
 fn main() -> Result<()> {
     color_eyre::install()?;
     let name = env!("CARGO_PKG_NAME");
+
+    // day09 rope [knots] [rule] [filename]
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("rope") {
+        let num_knots = args.get(2).map_or(Ok(10), |s| s.parse())?;
+        let rule = args
+            .get(3)
+            .map_or(Ok(FollowRule::DiagonalFirst), |s| s.parse())?;
+        let filename = args
+            .get(4)
+            .map_or(format!("inputs/{name}.1"), String::clone);
+        return describe_rope(load_input(&filename)?, num_knots, rule);
+    }
+
+    // day09 frames <-|file.svg|directory> [knots] [rule] [filename]
+    if args.get(1).map(String::as_str) == Some("frames") {
+        let output = args.get(2).ok_or(eyre!("where should the frames go?"))?;
+        let num_knots = args.get(3).map_or(Ok(10), |s| s.parse())?;
+        let rule = args
+            .get(4)
+            .map_or(Ok(FollowRule::DiagonalFirst), |s| s.parse())?;
+        let filename = args
+            .get(5)
+            .map_or(format!("inputs/{name}.1"), String::clone);
+        return export_frames(load_input(&filename)?, num_knots, rule, output);
+    }
+
     select_and_solve(
         format!("inputs/{name}.1").as_str(),
         part1,
@@ -26,65 +59,71 @@ fn part1(input: Vec<String>) -> Result<String> {
     // more than 1 unit orthogonally or diagonally.
     // When catching up, if a diagonal move is necessary, it occurs first.
     // Keep track of all the unique locations visited by T.
+    let rope = simulate(parse_moves(input)?, 2);
+    Ok(rope.tail_visited().len().to_string())
+}
 
-    let moves = parse_moves(input)?;
-
-    let mut visited: HashSet<Coord> = HashSet::new();
-
-    let mut head = Coord(0, 0);
-    let mut tail = head;
-
-    // T starts at same location as H
-    visited.insert(tail);
+fn part2(input: Vec<String>) -> Result<String> {
+    // As part 1, but with nine knots following the head, each catching up
+    // with the one before. Keep track of all the unique locations visited
+    // by the last.
+    let rope = simulate(parse_moves(input)?, 10);
+    Ok(rope.tail_visited().len().to_string())
+}
 
-    let top_left = Coord(-5, 5);
-    let bottom_right = Coord(5, -5);
-    println!("start:");
-    print_grid(&head, &tail, &visited, top_left, bottom_right);
+// Where each knot ends up, and how many places it visited on the way
+fn describe_rope(input: Vec<String>, num_knots: usize, rule: FollowRule) -> Result<()> {
+    if num_knots == 0 {
+        return Err(eyre!("a rope needs at least one knot"));
+    }
+    let mut rope = Rope::with_rule(num_knots, rule);
+    for mv in split_moves(parse_moves(input)?) {
+        rope.step(mv);
+    }
 
-    for mv in moves {
-        head = head.move_by(&mv);
-        println!("head moves to: {mv:?}");
-        print_grid(&head, &tail, &visited, top_left, bottom_right);
+    println!("head at {:?}, tail at {:?}", rope.head(), rope.tail());
+    for (i, knot) in rope.knots().iter().enumerate() {
+        println!("knot {i} at {knot:?}, visited {}", rope.visited(i).len());
+    }
+    Ok(())
+}
 
-        let tail_moves = catch_up(&head, &tail);
-        for mv in tail_moves {
-            tail = mv;
-            visited.insert(tail);
+// Every step of the simulation, as text on stdout ("-"), as an animated SVG
+// (a file ending in .svg) or as one SVG per step in a directory
+fn export_frames(
+    input: Vec<String>,
+    num_knots: usize,
+    rule: FollowRule,
+    output: &str,
+) -> Result<()> {
+    if num_knots == 0 {
+        return Err(eyre!("a rope needs at least one knot"));
+    }
+    let mut rope = Rope::with_rule(num_knots, rule);
+    let recording = record(&mut rope, split_moves(parse_moves(input)?));
 
-            println!("tail moves to: {mv:?}");
-            print_grid(&head, &tail, &visited, top_left, bottom_right);
+    if output == "-" {
+        for frame in 0..recording.frames.len() {
+            println!("{}", recording.to_text(frame));
+        }
+    } else if output.ends_with(".svg") {
+        fs::write(output, recording.animated_svg(0.1))?;
+    } else {
+        fs::create_dir_all(output)?;
+        for frame in 0..recording.frames.len() {
+            let path = Path::new(output).join(format!("frame-{frame:05}.svg"));
+            fs::write(path, recording.frame_svg(frame))?;
         }
     }
-
-    Ok(visited.len().to_string())
+    Ok(())
 }
 
-fn print_grid(
-    head: &Coord,
-    tail: &Coord,
-    visited: &HashSet<Coord>,
-    top_left: Coord,
-    bottom_right: Coord,
-) {
-    let (min_x, max_x) = (top_left.0, bottom_right.0);
-    let (max_y, min_y) = (top_left.1, bottom_right.1);
-    for y in (min_y..=max_y).rev() {
-        for x in min_x..=max_x {
-            if Coord(x, y) == *head {
-                print!("H")
-            } else if Coord(x, y) == *tail {
-                print!("T");
-            } else if visited.contains(&Coord(x, y)) {
-                print!("#");
-            } else if x == 0 && y == 0 {
-                print!("s");
-            } else {
-                print!(".");
-            }
-        }
-        println!();
+fn simulate(moves: Vec<Coord>, num_knots: usize) -> Rope {
+    let mut rope = Rope::new(num_knots);
+    for mv in split_moves(moves) {
+        rope.step(mv);
     }
+    rope
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Add, Sub)]
@@ -102,115 +141,18 @@ impl Coord {
     }
 }
 
-fn catch_up(head: &Coord, tail: &Coord) -> Vec<Coord> {
-    let mut tail = *tail;
-    let mut tail_moves = vec![];
-    loop {
-        let distance = tail.chebyshev_distance(head);
-        if distance <= 1 {
-            return tail_moves;
-        }
-
-        // prioritise diagonal movement
-        let mv = match *head - tail {
-            Coord(0, y) if y > 0 => Coord(0, 1),
-            Coord(0, y) if y < 0 => Coord(0, -1),
-            Coord(x, 0) if x > 0 => Coord(1, 0),
-            Coord(x, 0) if x < 0 => Coord(-1, 0),
-            Coord(x, y) => Coord(x.signum(), y.signum()),
-            //_ => unreachable!(),
-        };
-
-        tail = tail + mv;
-        tail_moves.push(tail);
-    }
-}
-
-fn parse_move(input: &str) -> IResult<&str, Coord> {
-    let (input, (_, direction, _, distance)) = tuple((
-        multispace0,
-        nom::character::complete::alpha1,
-        multispace0,
-        map_res(digit1, |s: &str| s.parse::<isize>()),
-    ))(input)?;
-
-    match direction {
-        "U" => Ok((input, Coord(0, distance))),
-        "D" => Ok((input, Coord(0, -distance))),
-        "L" => Ok((input, Coord(-distance, 0))),
-        "R" => Ok((input, Coord(distance, 0))),
-        _ => Err(nom::Err::Error(nom::error::Error::new(
-            "invalid direction",
-            nom::error::ErrorKind::Alpha,
-        ))),
-    }
+// A direction and how many steps to take in it, e.g. "R 4" or "UL 2"
+fn parse_move(i: Span) -> ParseResult<Coord> {
+    let (i, _) = space0(i)?;
+    let (rest, (direction, count)) = direction_count(i)?;
+    let (dx, dy) = direction.offset();
+    let count = count as isize;
+    Ok((rest, Coord(dx * count, dy * count)))
 }
 
 fn parse_moves(input: Vec<String>) -> Result<Vec<Coord>> {
-    let parsed_moves: Result<Vec<(&str, Coord)>, nom::Err<nom::error::Error<&str>>> =
-        input.iter().map(|s| parse_move(s)).collect();
-
-    let moves: Result<Vec<Coord>, nom::Err<nom::error::Error<&str>>> =
-        parsed_moves.map(|parsed_move| parsed_move.into_iter().map(|(_, m)| m).collect());
-
-    moves.map_err(|e| eyre!("parse failed: {}", e))
-}
-
-fn part2(input: Vec<String>) -> Result<String> {
-    // H, T(1..9) starts at (0, 0).
-    // For each instruction, H moves a number of steps in a single direction.
-    // Each T in ascending order then moves to "catch up" with the preceding knot,
-    // reducing the distance between it and the knot to at no
-    // more than 1 unit orthogonally or diagonally.
-    // When catching up, if a diagonal move is necessary, it occurs first.
-    // Keep track of all the unique locations visited by T[9].
-
-    let moves = parse_moves(input)?;
-
-    // To simulate as per part 2, we need to split the moves into
-    // single-move moves:
-    let moves = split_moves(moves);
-
-    let mut visited: HashSet<Coord> = HashSet::new();
-
-    const NUM_KNOTS: usize = 10;
-    const HEAD: usize = 0;
-    const T9: usize = NUM_KNOTS - 1;
-
-    let mut knots = [Coord(0, 0); NUM_KNOTS];
-
-    // T9 starts at same location as T8, ..., H
-    visited.insert(knots[T9]);
-
-    let top_left = Coord(-11, 11);
-    let bottom_right = Coord(15, -11);
-    println!("start:");
-    print_grid_v2(&knots, &visited, top_left, bottom_right);
-
-    let last_idx = knots.len() - 1;
-
-    for mv in moves {
-        knots[HEAD] = knots[HEAD].move_by(&mv);
-        println!("head moves to: {mv:?}");
-
-        for i in 1..knots.len() {
-            let prev_knot = knots[i - 1];
-            let tail_moves = catch_up(&prev_knot, &knots[i]);
-            for mv in tail_moves {
-                knots[i] = mv;
-
-                // Add T9 to the record of locations
-                if i == last_idx {
-                    visited.insert(knots[i]);
-                }
-
-                println!("T{i} moves to: {mv:?}");
-            }
-        }
-    }
-    print_grid_v2(&knots, &visited, top_left, bottom_right);
-
-    Ok(visited.len().to_string())
+    let text = input.join("\n");
+    parse_all(lines(parse_move), text.trim_end())
 }
 
 fn split_moves(moves: Vec<Coord>) -> Vec<Coord> {
@@ -218,48 +160,20 @@ fn split_moves(moves: Vec<Coord>) -> Vec<Coord> {
     // E.g. Coord(3, 0) becomes [Coord(1, 0), Coord(1, 0), Coord(1, 0)]
     let mut new_moves: Vec<_> = vec![];
     for mv in moves {
-        let c = mv.0 + mv.1; // since orthogonal, either is zero
-        for _ in 0..c.abs() {
+        // orthogonal or diagonal, so both directions take the same number
+        // of steps, unless one is zero
+        let c = mv.0.abs().max(mv.1.abs());
+        for _ in 0..c {
             new_moves.push(Coord(mv.0.signum(), mv.1.signum()));
         }
     }
     new_moves
 }
 
-fn print_grid_v2(knots: &[Coord], visited: &HashSet<Coord>, top_left: Coord, bottom_right: Coord) {
-    let (min_x, max_x) = (top_left.0, bottom_right.0);
-    let (max_y, min_y) = (top_left.1, bottom_right.1);
-    for y in (min_y..=max_y).rev() {
-        for x in min_x..=max_x {
-            let mut knot_printed = false;
-            for (i, knot) in knots.iter().enumerate() {
-                if Coord(x, y) == *knot {
-                    if i == 0 {
-                        print!("H");
-                    } else {
-                        print!("{}", i);
-                    }
-                    knot_printed = true;
-                    break;
-                }
-            }
-            if !knot_printed {
-                if visited.contains(&Coord(x, y)) {
-                    print!("#");
-                } else if x == 0 && y == 0 {
-                    print!("s");
-                } else {
-                    print!(".");
-                }
-            }
-        }
-        println!();
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::parse::parse_prefix;
     use rstest::*;
 
     #[fixture]
@@ -292,10 +206,40 @@ R 2
 
     #[test]
     fn test_parse_move() {
-        assert_eq!(parse_move("U 1"), Ok(("", Coord(0, 1))));
-        assert_eq!(parse_move("D 2"), Ok(("", Coord(0, -2))));
-        assert_eq!(parse_move("L 13"), Ok(("", Coord(-13, 0))));
-        assert_eq!(parse_move("R 0"), Ok(("", Coord(0, 0))));
+        let parse = |s| parse_prefix(parse_move, s).unwrap();
+        assert_eq!(parse("U 1"), ("", Coord(0, 1)));
+        assert_eq!(parse("D 2"), ("", Coord(0, -2)));
+        assert_eq!(parse("L 13"), ("", Coord(-13, 0)));
+        assert_eq!(parse("R 0"), ("", Coord(0, 0)));
+    }
+
+    #[test]
+    fn test_parse_diagonal_move() {
+        let parse = |s| parse_prefix(parse_move, s).unwrap();
+        assert_eq!(parse("UL 2"), ("", Coord(-2, 2)));
+        assert_eq!(parse("UR 1"), ("", Coord(1, 1)));
+        assert_eq!(parse("DL 3"), ("", Coord(-3, -3)));
+        assert_eq!(parse("DR 4"), ("", Coord(4, -4)));
+    }
+
+    #[rstest]
+    #[case("X 3", "line 3, column 1", "unknown direction \"X\"")]
+    #[case("LU 3", "line 3, column 1", "unknown direction \"LU\"")]
+    #[case("3", "line 3, column 1", "unknown direction \"\"")]
+    #[case("U", "line 3, column 2", "missing count")]
+    #[case("U ", "line 3, column 3", "expected an ascii digit")]
+    #[case("D -2", "line 3, column 3", "negative count -2")]
+    #[case("D 2 3", "line 3, column 4", "expected eof")]
+    fn test_parse_moves_error(
+        mut input: Vec<String>,
+        #[case] line: &str,
+        #[case] position: &str,
+        #[case] reason: &str,
+    ) {
+        input[2] = line.to_owned();
+        let e = parse_moves(input).unwrap_err().to_string();
+        assert!(e.contains(&format!("bad input at {position}")), "{e}");
+        assert!(e.contains(reason), "{e}");
     }
 
     #[rstest]
@@ -315,41 +259,6 @@ R 2
         );
     }
 
-    #[test]
-    fn test_catch_up_right() {
-        let head = Coord(2, 0);
-        let tail = Coord(0, 0);
-        assert_eq!(catch_up(&head, &tail), vec![Coord(1, 0)]);
-    }
-
-    #[test]
-    fn test_catch_up_left() {
-        let head = Coord(-3, 0);
-        let tail = Coord(0, 0);
-        assert_eq!(catch_up(&head, &tail), vec![Coord(-1, 0), Coord(-2, 0)]);
-    }
-
-    #[test]
-    fn test_catch_up_up() {
-        let head = Coord(3, 4);
-        let tail = Coord(3, 1);
-        assert_eq!(catch_up(&head, &tail), vec![Coord(3, 2), Coord(3, 3)]);
-    }
-
-    #[test]
-    fn test_catch_up_diagonal() {
-        let head = Coord(3, 3);
-        let tail = Coord(0, 0);
-        assert_eq!(catch_up(&head, &tail), vec![Coord(1, 1), Coord(2, 2)]);
-    }
-
-    #[test]
-    fn test_catch_up_diagonal_then_up() {
-        let head = Coord(1, 3);
-        let tail = Coord(0, 0);
-        assert_eq!(catch_up(&head, &tail), vec![Coord(1, 1), Coord(1, 2)]);
-    }
-
     #[fixture]
     fn larger_input() -> Vec<String> {
         "
@@ -379,5 +288,6 @@ U 20
         assert_eq!(split_moves(vec![Coord(0, 0)]), vec![]);
         assert_eq!(split_moves(vec![Coord(1, 0)]), vec![Coord(1, 0)]);
         assert_eq!(split_moves(vec![Coord(-3, 0)]), vec![Coord(-1, 0); 3]);
+        assert_eq!(split_moves(vec![Coord(2, -2)]), vec![Coord(1, -1); 2]);
     }
 }
@@ -0,0 +1,291 @@
+// A rope of any number of knots. The head is moved one step at a time, and
+// every other knot follows the one ahead of it according to a FollowRule.
+// Each knot remembers every position it has visited.
+
+use crate::Coord;
+use color_eyre::eyre::{eyre, Report, Result};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowRule {
+    // Stay touching the knot ahead, moving diagonally first when catching up.
+    // This is the puzzle's rule.
+    DiagonalFirst,
+
+    // Only move up, down, left or right, and stay touching the knot ahead
+    // along an edge, closing the larger gap first.
+    Orthogonal,
+
+    // As DiagonalFirst, but allow the knot to fall up to this far behind
+    Slack(usize),
+}
+
+// "diagonal", "orthogonal" or "slack:N"
+impl FromStr for FollowRule {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let rule = match s.split_once(':') {
+            None if s == "diagonal" => FollowRule::DiagonalFirst,
+            None if s == "orthogonal" => FollowRule::Orthogonal,
+            Some(("slack", n)) => FollowRule::Slack(n.parse()?),
+            _ => return Err(eyre!("Invalid follow rule {s}")),
+        };
+        Ok(rule)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rope {
+    knots: Vec<Coord>, // head first
+    visited: Vec<HashSet<Coord>>,
+    rule: FollowRule,
+}
+
+impl Rope {
+    pub fn new(num_knots: usize) -> Self {
+        Self::with_rule(num_knots, FollowRule::DiagonalFirst)
+    }
+
+    pub fn with_rule(num_knots: usize, rule: FollowRule) -> Self {
+        assert!(num_knots > 0, "a rope needs at least one knot");
+        let start = Coord(0, 0);
+        Self {
+            knots: vec![start; num_knots],
+            visited: vec![HashSet::from([start]); num_knots],
+            rule,
+        }
+    }
+
+    // Move the head one step, e.g. Coord(0, 1) for up, and let the rest follow
+    pub fn step(&mut self, direction: Coord) {
+        assert!(
+            direction.0.abs() <= 1 && direction.1.abs() <= 1,
+            "{direction:?} is more than one step"
+        );
+
+        self.knots[0] = self.knots[0].move_by(&direction);
+        self.visited[0].insert(self.knots[0]);
+
+        for i in 1..self.knots.len() {
+            for position in follow(self.rule, &self.knots[i - 1], &self.knots[i]) {
+                self.knots[i] = position;
+                self.visited[i].insert(position);
+            }
+        }
+    }
+
+    pub fn knots(&self) -> &[Coord] {
+        &self.knots
+    }
+
+    pub fn head(&self) -> Coord {
+        self.knots[0]
+    }
+
+    pub fn tail(&self) -> Coord {
+        *self.knots.last().expect("at least one knot")
+    }
+
+    pub fn visited(&self, knot: usize) -> &HashSet<Coord> {
+        &self.visited[knot]
+    }
+
+    pub fn tail_visited(&self) -> &HashSet<Coord> {
+        self.visited.last().expect("at least one knot")
+    }
+}
+
+// The positions a knot moves through to follow the one ahead of it
+fn follow(rule: FollowRule, ahead: &Coord, knot: &Coord) -> Vec<Coord> {
+    match rule {
+        FollowRule::DiagonalFirst => catch_up(ahead, knot),
+        FollowRule::Orthogonal => catch_up_orthogonally(ahead, knot),
+        FollowRule::Slack(slack) => catch_up_within(ahead, knot, slack),
+    }
+}
+
+pub fn catch_up(head: &Coord, tail: &Coord) -> Vec<Coord> {
+    catch_up_within(head, tail, 1)
+}
+
+fn catch_up_within(head: &Coord, tail: &Coord, slack: usize) -> Vec<Coord> {
+    let mut tail = *tail;
+    let mut tail_moves = vec![];
+    loop {
+        let distance = tail.chebyshev_distance(head);
+        if distance <= slack {
+            return tail_moves;
+        }
+
+        // prioritise diagonal movement
+        let mv = match *head - tail {
+            Coord(0, y) if y > 0 => Coord(0, 1),
+            Coord(0, y) if y < 0 => Coord(0, -1),
+            Coord(x, 0) if x > 0 => Coord(1, 0),
+            Coord(x, 0) if x < 0 => Coord(-1, 0),
+            Coord(x, y) => Coord(x.signum(), y.signum()),
+        };
+
+        tail = tail + mv;
+        tail_moves.push(tail);
+    }
+}
+
+fn catch_up_orthogonally(head: &Coord, tail: &Coord) -> Vec<Coord> {
+    let mut tail = *tail;
+    let mut tail_moves = vec![];
+    loop {
+        let d = *head - tail;
+        if d.0.abs() + d.1.abs() <= 1 {
+            return tail_moves;
+        }
+
+        // horizontal first when the gaps are equal
+        let mv = if d.0.abs() >= d.1.abs() {
+            Coord(d.0.signum(), 0)
+        } else {
+            Coord(0, d.1.signum())
+        };
+
+        tail = tail + mv;
+        tail_moves.push(tail);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UP: Coord = Coord(0, 1);
+    const RIGHT: Coord = Coord(1, 0);
+
+    fn rope_after(mut rope: Rope, steps: &[(Coord, usize)]) -> Rope {
+        for &(direction, count) in steps {
+            for _ in 0..count {
+                rope.step(direction);
+            }
+        }
+        rope
+    }
+
+    #[test]
+    fn test_catch_up_right() {
+        let head = Coord(2, 0);
+        let tail = Coord(0, 0);
+        assert_eq!(catch_up(&head, &tail), vec![Coord(1, 0)]);
+    }
+
+    #[test]
+    fn test_catch_up_left() {
+        let head = Coord(-3, 0);
+        let tail = Coord(0, 0);
+        assert_eq!(catch_up(&head, &tail), vec![Coord(-1, 0), Coord(-2, 0)]);
+    }
+
+    #[test]
+    fn test_catch_up_up() {
+        let head = Coord(3, 4);
+        let tail = Coord(3, 1);
+        assert_eq!(catch_up(&head, &tail), vec![Coord(3, 2), Coord(3, 3)]);
+    }
+
+    #[test]
+    fn test_catch_up_diagonal() {
+        let head = Coord(3, 3);
+        let tail = Coord(0, 0);
+        assert_eq!(catch_up(&head, &tail), vec![Coord(1, 1), Coord(2, 2)]);
+    }
+
+    #[test]
+    fn test_catch_up_diagonal_then_up() {
+        let head = Coord(1, 3);
+        let tail = Coord(0, 0);
+        assert_eq!(catch_up(&head, &tail), vec![Coord(1, 1), Coord(1, 2)]);
+    }
+
+    #[test]
+    fn test_catch_up_orthogonally() {
+        let head = Coord(2, 1);
+        let tail = Coord(0, 0);
+        assert_eq!(
+            catch_up_orthogonally(&head, &tail),
+            vec![Coord(1, 0), Coord(2, 0)]
+        );
+        assert_eq!(
+            catch_up_orthogonally(&Coord(1, 1), &tail),
+            vec![Coord(1, 0)]
+        );
+        assert_eq!(catch_up_orthogonally(&Coord(0, -1), &tail), vec![]);
+    }
+
+    #[test]
+    fn test_catch_up_within() {
+        let tail = Coord(0, 0);
+        assert_eq!(catch_up_within(&Coord(3, 0), &tail, 3), vec![]);
+        assert_eq!(catch_up_within(&Coord(4, 1), &tail, 3), vec![Coord(1, 1)]);
+        assert_eq!(
+            catch_up_within(&Coord(0, 2), &tail, 0),
+            vec![Coord(0, 1), Coord(0, 2)]
+        );
+    }
+
+    #[test]
+    fn test_rope() {
+        let rope = rope_after(Rope::new(3), &[(RIGHT, 4), (UP, 2)]);
+        assert_eq!(rope.knots(), &[Coord(4, 2), Coord(4, 1), Coord(3, 1)]);
+        assert_eq!(rope.head(), Coord(4, 2));
+        assert_eq!(rope.tail(), Coord(3, 1));
+        assert_eq!(rope.visited(0).len(), 7);
+        assert_eq!(rope.visited(1).len(), 5);
+        assert_eq!(rope.tail_visited().len(), 4);
+    }
+
+    #[test]
+    fn test_rope_of_one() {
+        let rope = rope_after(Rope::new(1), &[(RIGHT, 2)]);
+        assert_eq!(rope.head(), rope.tail());
+        assert_eq!(rope.tail_visited().len(), 3);
+    }
+
+    #[test]
+    fn test_rope_rules() {
+        let steps = [(RIGHT, 2), (UP, 2)];
+
+        let rope = rope_after(Rope::with_rule(2, FollowRule::Orthogonal), &steps);
+        assert_eq!(rope.tail(), Coord(2, 1));
+
+        let rope = rope_after(Rope::with_rule(2, FollowRule::Slack(2)), &steps);
+        assert_eq!(rope.tail(), Coord(0, 0));
+
+        // a slack of one is the puzzle's rule
+        let slack = rope_after(Rope::with_rule(2, FollowRule::Slack(1)), &steps);
+        let puzzle = rope_after(Rope::new(2), &steps);
+        assert_eq!(slack.knots(), puzzle.knots());
+    }
+
+    #[test]
+    fn test_follow_rule_from_str() {
+        assert_eq!(
+            "diagonal".parse::<FollowRule>().unwrap(),
+            FollowRule::DiagonalFirst
+        );
+        assert_eq!(
+            "orthogonal".parse::<FollowRule>().unwrap(),
+            FollowRule::Orthogonal
+        );
+        assert_eq!(
+            "slack:3".parse::<FollowRule>().unwrap(),
+            FollowRule::Slack(3)
+        );
+        assert!("slack".parse::<FollowRule>().is_err());
+        assert!("slack:x".parse::<FollowRule>().is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "more than one step")]
+    fn test_step_too_far() {
+        Rope::new(2).step(Coord(2, 0));
+    }
+}
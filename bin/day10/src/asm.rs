@@ -0,0 +1,209 @@
+// A two-pass assembler for the CPU.
+//
+// Each line holds an optional label, an optional instruction and an optional
+// comment, e.g.
+//
+//   loop: sub y, 1   ; count down
+//         jnz y, loop
+//
+// The first pass parses every line and works out the address each label
+// refers to; the second resolves the labels used by jumps. Mnemonics are
+// looked up in the cycle table, so anything not in it is reported as an
+// unknown instruction.
+
+use crate::{parse_register, Condition, Instruction, CYCLES};
+use color_eyre::eyre::{eyre, Report, Result};
+use common::parse::{parse_all, BadInput, ParseResult, Span};
+use common::parsers::lines;
+use nom::bytes::complete::{tag, take_till, take_while1};
+use nom::character::complete::{char, space0};
+use nom::combinator::{cut, map, opt, recognize};
+use nom::error::{ErrorKind, FromExternalError};
+use nom::sequence::{pair, preceded, separated_pair, terminated};
+use nom_supreme::error::ErrorTree;
+use std::collections::HashMap;
+
+#[derive(thiserror::Error, Debug)]
+enum AsmError {
+    #[error("unknown instruction {0:?}")]
+    UnknownInstruction(String),
+
+    #[error("unknown label {0:?}")]
+    UnknownLabel(String),
+
+    #[error("label {0:?} is already defined")]
+    DuplicateLabel(String),
+}
+
+// An instruction, or a jump to a label that is yet to be resolved
+#[derive(Debug)]
+enum Statement<'a> {
+    Instruction(Instruction),
+    Jump(Condition, Span<'a>),
+}
+
+#[derive(Debug)]
+struct Line<'a> {
+    label: Option<Span<'a>>,
+    statement: Option<Statement<'a>>,
+}
+
+fn error_tree(location: Span, e: AsmError) -> ErrorTree<Span> {
+    ErrorTree::from_external_error(location, ErrorKind::Verify, e)
+}
+
+// An error found after parsing, reported in the same way as a parse error
+fn bad_input(program: &str, location: Span, e: AsmError) -> Report {
+    eyre!(BadInput::new(program, &error_tree(location, e)).render())
+}
+
+fn identifier(i: Span) -> ParseResult<Span> {
+    take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_')(i)
+}
+
+fn label(i: Span) -> ParseResult<Span> {
+    terminated(identifier, char(':'))(i)
+}
+
+fn comment(i: Span) -> ParseResult<Span> {
+    recognize(pair(char(';'), take_till(|c| c == '\n')))(i)
+}
+
+fn statement(i: Span) -> ParseResult<Statement> {
+    let (_, word) = identifier(i)?;
+    let jump_if = |mnemonic, condition: fn(_) -> Condition| {
+        cut(map(
+            preceded(
+                tag(mnemonic),
+                separated_pair(parse_register, tag(", "), identifier),
+            ),
+            move |(register, label)| Statement::Jump(condition(register), label),
+        ))
+    };
+
+    match *word.fragment() {
+        "jmp" => cut(map(preceded(tag("jmp "), identifier), |label| {
+            Statement::Jump(Condition::Always, label)
+        }))(i),
+        "jz" => jump_if("jz ", Condition::Zero)(i),
+        "jnz" => jump_if("jnz ", Condition::NotZero)(i),
+        mnemonic if CYCLES.iter().any(|(m, _)| *m == mnemonic) => {
+            cut(map(Instruction::parse, Statement::Instruction))(i)
+        }
+        mnemonic => Err(nom::Err::Failure(error_tree(
+            word,
+            AsmError::UnknownInstruction(mnemonic.to_owned()),
+        ))),
+    }
+}
+
+fn line(i: Span) -> ParseResult<Line> {
+    let (i, _) = space0(i)?;
+    let (i, label) = opt(terminated(label, space0))(i)?;
+    let (i, statement) = opt(statement)(i)?;
+    let (i, _) = space0(i)?;
+    let (i, _) = opt(comment)(i)?;
+    Ok((i, Line { label, statement }))
+}
+
+pub fn assemble(program: &str) -> Result<Vec<Instruction>> {
+    let program = program.trim_end();
+    let lines = parse_all(lines(line), program)?;
+
+    // first pass: where each label points
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut address = 0;
+    for line in &lines {
+        if let Some(label) = line.label {
+            if labels.insert(label.fragment(), address).is_some() {
+                let e = AsmError::DuplicateLabel(label.fragment().to_string());
+                return Err(bad_input(program, label, e));
+            }
+        }
+        if line.statement.is_some() {
+            address += 1;
+        }
+    }
+
+    // second pass: resolve jumps
+    lines
+        .into_iter()
+        .filter_map(|line| line.statement)
+        .map(|statement| match statement {
+            Statement::Instruction(instruction) => Ok(instruction),
+            Statement::Jump(condition, label) => match labels.get(label.fragment()) {
+                Some(address) => Ok(Instruction::Jump(condition, *address)),
+                None => {
+                    let e = AsmError::UnknownLabel(label.fragment().to_string());
+                    Err(bad_input(program, label, e))
+                }
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Operand, Register};
+
+    #[test]
+    fn test_assemble() {
+        let program = "
+; count y down from 3
+        set y, 3
+loop:   sub y, 1    ; y = y - 1
+        jnz y, loop
+        jmp end
+        addx 5      ; skipped
+end:
+";
+        assert_eq!(
+            assemble(program).unwrap(),
+            vec![
+                Instruction::Set(Register::Y, Operand::Value(3)),
+                Instruction::Sub(Register::Y, Operand::Value(1)),
+                Instruction::Jump(Condition::NotZero(Register::Y), 1),
+                Instruction::Jump(Condition::Always, 5),
+                Instruction::Addx(5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assemble_label_and_instruction() {
+        assert_eq!(
+            assemble("start: noop\njz x, start").unwrap(),
+            vec![
+                Instruction::Noop,
+                Instruction::Jump(Condition::Zero(Register::X), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        let e = assemble("noop\n  addy 4").unwrap_err().to_string();
+        assert!(e.contains("bad input at line 2, column 3"), "{e}");
+        assert!(e.contains("unknown instruction \"addy\""), "{e}");
+
+        let e = assemble("noop\njmp nowhere").unwrap_err().to_string();
+        assert!(e.contains("bad input at line 2, column 5"), "{e}");
+        assert!(e.contains("unknown label \"nowhere\""), "{e}");
+
+        let e = assemble("a: noop\na: noop").unwrap_err().to_string();
+        assert!(e.contains("bad input at line 2, column 1"), "{e}");
+        assert!(e.contains("label \"a\" is already defined"), "{e}");
+
+        // a known instruction with bad operands
+        let e = assemble("mul q, 2").unwrap_err().to_string();
+        assert!(e.contains("bad input at line 1, column 5"), "{e}");
+        assert!(
+            e.contains("expected 'x' or expected 'y' or expected 'z'"),
+            "{e}"
+        );
+
+        let e = assemble("noop x").unwrap_err().to_string();
+        assert!(e.contains("bad input at line 1, column 6"), "{e}");
+    }
+}
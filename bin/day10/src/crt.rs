@@ -0,0 +1,141 @@
+// The CRT: 40 pixels wide and 6 high, drawn one pixel per cycle, left to
+// right and top to bottom. A pixel is lit if the sprite, three pixels wide
+// and centred on X, covers it while it is being drawn.
+
+use crate::observer::{CycleState, Observer};
+use color_eyre::eyre::{eyre, Report, Result};
+use std::fmt;
+use std::str::FromStr;
+
+pub const WIDTH: usize = 40;
+pub const HEIGHT: usize = 6;
+
+// Whether the sprite centred on X covers a column. X can be anything a
+// register holds, so this must not overflow at either end.
+pub fn sprite_covers(x: i32, column: usize) -> bool {
+    (column as i32).abs_diff(x) <= 1
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crt {
+    pixels: [[bool; WIDTH]; HEIGHT],
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crt {
+    pub fn new() -> Self {
+        Self {
+            pixels: [[false; WIDTH]; HEIGHT],
+        }
+    }
+
+    // Draw the pixel for a cycle (counting from 1), given X during it.
+    // Programs that run for longer than a frame draw over the top of
+    // earlier frames.
+    pub fn draw(&mut self, cycle: usize, x: i32) {
+        let position = (cycle - 1) % (WIDTH * HEIGHT);
+        let (row, column) = (position / WIDTH, position % WIDTH);
+        self.pixels[row][column] = sprite_covers(x, column);
+    }
+
+    pub fn lit(&self, row: usize, column: usize) -> bool {
+        self.pixels[row][column]
+    }
+}
+
+impl Observer for Crt {
+    fn during(&mut self, state: &CycleState) {
+        self.draw(state.cycle, state.x);
+    }
+}
+
+// One row of '#' (lit) and '.' (dark) per line
+impl fmt::Display for Crt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.pixels {
+            let line: String = row.iter().map(|&lit| if lit { '#' } else { '.' }).collect();
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Crt {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut crt = Self::new();
+        let rows: Vec<&str> = s.lines().collect();
+        if rows.len() != HEIGHT {
+            return Err(eyre!("expected {HEIGHT} rows, found {}", rows.len()));
+        }
+        for (row, line) in rows.into_iter().enumerate() {
+            if line.chars().count() != WIDTH {
+                return Err(eyre!("row {row} should be {WIDTH} pixels wide"));
+            }
+            for (column, c) in line.chars().enumerate() {
+                crt.pixels[row][column] = match c {
+                    '#' => true,
+                    '.' => false,
+                    other => return Err(eyre!("unexpected pixel {other:?}")),
+                };
+            }
+        }
+        Ok(crt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw() {
+        let mut crt = Crt::new();
+        crt.draw(1, 1);
+        crt.draw(2, 1);
+        crt.draw(3, 1);
+        crt.draw(4, 1);
+        crt.draw(41, 0);
+        crt.draw(42, 5);
+        assert!(crt.lit(0, 0) && crt.lit(0, 1) && crt.lit(0, 2));
+        assert!(!crt.lit(0, 3));
+        assert!(crt.lit(1, 0));
+        assert!(!crt.lit(1, 1));
+
+        // the next frame starts at the top again
+        crt.draw(241, 5);
+        assert!(!crt.lit(0, 0));
+    }
+
+    #[test]
+    fn test_draw_extreme_x() {
+        let mut crt = Crt::new();
+        crt.draw(1, i32::MAX);
+        crt.draw(2, i32::MIN);
+        crt.draw(3, -1);
+        assert!(!crt.lit(0, 0) && !crt.lit(0, 1) && !crt.lit(0, 2));
+
+        crt.draw(1, -1);
+        assert!(crt.lit(0, 0));
+        assert!(!sprite_covers(i32::MAX, WIDTH - 1));
+        assert!(!sprite_covers(i32::MIN, 0));
+    }
+
+    #[test]
+    fn test_display_and_from_str() {
+        let mut crt = Crt::new();
+        crt.draw(2, 1);
+        let text = crt.to_string();
+        assert!(text.starts_with(".#......"));
+        assert_eq!(text.lines().count(), HEIGHT);
+        assert_eq!(text.parse::<Crt>().unwrap(), crt);
+
+        assert!("#.#".parse::<Crt>().is_err());
+    }
+}
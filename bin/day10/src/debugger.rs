@@ -0,0 +1,478 @@
+// Run a program a cycle at a time, stopping at breakpoints on the cycle
+// number or program counter, or when X meets a watched condition.
+//
+// The CPU is deterministic, so rewinding to an earlier cycle replays the
+// program from the start rather than keeping every state. Cycles are run
+// just as execute_with() runs them, and an observer keeps X both during and
+// at the end of each one, so the value during any cycle already run can be
+// looked up without an off-by-one in sight.
+
+use crate::observer::{CycleState, Observer};
+use crate::pipeline::{MicroOp, Timing};
+use crate::{execute_cycle, Cpu, Memory, Registers};
+use color_eyre::eyre::{eyre, Report, Result};
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    Cycle(usize),          // before this cycle starts
+    ProgramCounter(usize), // before the instruction at this address is loaded
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watch {
+    Equals(i32),
+    Above(i32),
+    Below(i32),
+    Changes,
+}
+
+// Why a run stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Breakpoint(Breakpoint),
+    Watch(Watch),
+    Halted,
+}
+
+impl Watch {
+    fn triggered(&self, before: i32, after: i32) -> bool {
+        match *self {
+            Watch::Equals(n) => after == n && before != n,
+            Watch::Above(n) => after > n && before <= n,
+            Watch::Below(n) => after < n && before >= n,
+            Watch::Changes => after != before,
+        }
+    }
+}
+
+// "== N", "> N", "< N" or "changes"
+impl FromStr for Watch {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let watch = match s.split_once(' ') {
+            None if s == "changes" => Watch::Changes,
+            Some(("==", n)) => Watch::Equals(n.parse()?),
+            Some((">", n)) => Watch::Above(n.parse()?),
+            Some(("<", n)) => Watch::Below(n.parse()?),
+            _ => return Err(eyre!("Invalid watch {s}")),
+        };
+        Ok(watch)
+    }
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Breakpoint(Breakpoint::Cycle(n)) => write!(f, "breakpoint at cycle {n}"),
+            Stop::Breakpoint(Breakpoint::ProgramCounter(pc)) => {
+                write!(f, "breakpoint at pc {pc}")
+            }
+            Stop::Watch(watch) => write!(f, "watch x {watch:?}"),
+            Stop::Halted => write!(f, "halted"),
+        }
+    }
+}
+
+// X during and at the end of each cycle run so far
+#[derive(Debug, Clone, Default)]
+struct History {
+    during: Vec<i32>,
+    after: Vec<i32>,
+}
+
+impl Observer for History {
+    fn during(&mut self, state: &CycleState) {
+        self.during.push(state.x);
+    }
+
+    fn end(&mut self, state: &CycleState) {
+        self.after.push(state.x);
+    }
+}
+
+pub struct Debugger<'a> {
+    memory: &'a Memory,
+    cpu: Cpu,
+    history: History,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<Watch>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(memory: &'a Memory, timing: Timing) -> Self {
+        Self {
+            memory,
+            cpu: Cpu::with_timing(timing),
+            history: History::default(),
+            breakpoints: vec![],
+            watches: vec![],
+        }
+    }
+
+    // Number of cycles run so far
+    pub fn cycles(&self) -> usize {
+        self.history.after.len()
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.cpu.registers
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.cpu.halted
+    }
+
+    // Micro-ops still to run for the current instruction, and the next one
+    pub fn pending_steps(&self) -> (usize, Option<&MicroOp>) {
+        (self.cpu.pipeline.len(), self.cpu.pipeline.front())
+    }
+
+    pub fn trace(&self) -> &[i32] {
+        &self.history.after
+    }
+
+    // X during a cycle (counting from 1) that has already run
+    pub fn x_during(&self, cycle: usize) -> Option<i32> {
+        self.history.during.get(cycle.checked_sub(1)?).copied()
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn add_watch(&mut self, watch: Watch) {
+        self.watches.push(watch);
+    }
+
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.watches.clear();
+    }
+
+    // Run one cycle, returning false if the CPU has halted
+    pub fn step(&mut self) -> bool {
+        if self.cpu.halted {
+            return false;
+        }
+        let number = self.cycles() + 1;
+        execute_cycle(&mut self.cpu, self.memory, number, &mut [&mut self.history])
+    }
+
+    // Run at least one cycle, then carry on until something stops us
+    pub fn run(&mut self) -> Stop {
+        loop {
+            let before = self.cpu.registers.x;
+            if !self.step() {
+                return Stop::Halted;
+            }
+            if let Some(stop) = self.check(before) {
+                return stop;
+            }
+        }
+    }
+
+    fn check(&self, before: i32) -> Option<Stop> {
+        let after = self.cpu.registers.x;
+        if let Some(watch) = self.watches.iter().find(|w| w.triggered(before, after)) {
+            return Some(Stop::Watch(*watch));
+        }
+
+        // what is about to happen in the next cycle
+        let next_cycle = self.cycles() + 1;
+        let loading = self.cpu.pipeline.is_empty();
+        let pc = self.cpu.registers.program_counter;
+        self.breakpoints
+            .iter()
+            .find(|b| match b {
+                Breakpoint::Cycle(n) => *n == next_cycle,
+                Breakpoint::ProgramCounter(address) => loading && *address == pc,
+            })
+            .map(|b| Stop::Breakpoint(*b))
+    }
+
+    // Go back to just after the given number of cycles
+    pub fn rewind(&mut self, cycles: usize) -> Result<()> {
+        if cycles > self.cycles() {
+            return Err(eyre!(
+                "cycle {cycles} hasn't been run yet, step or continue to get there"
+            ));
+        }
+        let history = std::mem::take(&mut self.history);
+        self.cpu = Cpu::with_timing(self.cpu.timing.clone());
+        while self.cycles() < cycles {
+            self.step();
+        }
+        debug_assert_eq!(self.history.after, history.after[..cycles]);
+        Ok(())
+    }
+}
+
+// Drive a debugger from line-based commands:
+//   s [n] (or empty line) - step n cycles, default 1
+//   c                     - continue to a breakpoint, watch or the end
+//   b N                   - break before cycle N
+//   bp N                  - break before loading the instruction at N
+//   w == N, w > N, w < N  - stop when x becomes equal to, above or below N
+//   w changes             - stop whenever x changes
+//   d                     - delete all breakpoints and watches
+//   r N                   - rewind to the end of cycle N
+//   x N                   - x during cycle N
+//   t                     - x at the end of each cycle so far
+//   q                     - quit
+// The registers and pending steps are shown after every command that runs.
+pub fn interact(
+    debugger: &mut Debugger,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> Result<()> {
+    writeln!(
+        output,
+        "{} instructions",
+        debugger.memory.instructions.len()
+    )?;
+    show(debugger, &mut output)?;
+
+    for line in input.lines() {
+        let line = line?;
+        let (command, arg) = line
+            .trim()
+            .split_once(' ')
+            .map_or((line.trim(), ""), |(c, arg)| (c, arg.trim()));
+        let number = || -> Result<usize> {
+            arg.parse()
+                .map_err(|_| eyre!("{command} needs a number, not {arg:?}"))
+        };
+
+        let result = match command {
+            "" | "s" => (if arg.is_empty() { Ok(1) } else { number() }).map(|n| {
+                for _ in 0..n {
+                    if !debugger.step() {
+                        break;
+                    }
+                }
+                true
+            }),
+            "c" => {
+                let stop = debugger.run();
+                writeln!(output, "stopped: {stop}")?;
+                Ok(true)
+            }
+            "b" => number().map(|n| {
+                debugger.add_breakpoint(Breakpoint::Cycle(n));
+                false
+            }),
+            "bp" => number().map(|n| {
+                debugger.add_breakpoint(Breakpoint::ProgramCounter(n));
+                false
+            }),
+            "w" => arg.parse().map(|watch| {
+                debugger.add_watch(watch);
+                false
+            }),
+            "d" => {
+                debugger.clear();
+                Ok(false)
+            }
+            "r" => number().and_then(|n| debugger.rewind(n)).map(|_| true),
+            "x" => match number() {
+                Ok(n) => {
+                    match debugger.x_during(n) {
+                        Some(x) => writeln!(output, "during cycle {n}: x {x}")?,
+                        None => writeln!(output, "cycle {n} hasn't started")?,
+                    }
+                    Ok(false)
+                }
+                Err(e) => Err(e),
+            },
+            "t" => {
+                for (i, x) in debugger.trace().iter().enumerate() {
+                    writeln!(output, "end of cycle {}: x {x}", i + 1)?;
+                }
+                Ok(false)
+            }
+            "q" => break,
+            other => Err(eyre!("unknown command: {other}")),
+        };
+
+        match result {
+            Ok(true) => show(debugger, &mut output)?,
+            Ok(false) => {}
+            Err(e) => writeln!(output, "{e}")?,
+        }
+    }
+
+    Ok(())
+}
+
+fn show(debugger: &Debugger, output: &mut impl Write) -> Result<()> {
+    let r = debugger.registers();
+    write!(
+        output,
+        "[cycle {}] pc {}, x {}, y {}, z {}",
+        debugger.cycles(),
+        r.program_counter,
+        r.x,
+        r.y,
+        r.z
+    )?;
+    match debugger.pending_steps() {
+        (_, None) if debugger.is_halted() => writeln!(output, ", halted")?,
+        (_, None) => writeln!(output)?,
+        (n, Some(next)) => writeln!(output, ", {n} steps pending, next {next:?}")?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::observer::Log;
+    use crate::pipeline::Alu;
+    use crate::{execute_with, Operand, Register};
+    use rstest::*;
+
+    #[fixture]
+    fn memory() -> Memory {
+        // the small example from the puzzle
+        Memory::new(&assemble("noop\naddx 3\naddx -5").unwrap())
+    }
+
+    #[rstest]
+    fn test_step(memory: Memory) {
+        let mut debugger = Debugger::new(&memory, Timing::default());
+        assert!(debugger.step());
+        assert_eq!(debugger.cycles(), 1);
+        assert!(debugger.step());
+        assert_eq!(
+            debugger.pending_steps(),
+            (
+                1,
+                Some(&MicroOp::Write(Register::X, Alu::Add, Operand::Value(3)))
+            )
+        );
+        assert!(debugger.step());
+        assert_eq!(debugger.registers().x, 4);
+    }
+
+    #[rstest]
+    fn test_run_matches_execute(memory: Memory) {
+        let mut debugger = Debugger::new(&memory, Timing::default());
+        assert_eq!(debugger.run(), Stop::Halted);
+        assert!(!debugger.step());
+
+        let mut log = Log::default();
+        let cycles = execute_with(&mut Cpu::new(), &memory, &mut [&mut log]);
+        assert_eq!(debugger.trace(), log.x_after());
+        assert_eq!(debugger.cycles(), cycles);
+    }
+
+    #[rstest]
+    #[case("stall")]
+    #[case("addx=3,branch=1")]
+    fn test_run_with_timing(#[case] timing: &str) {
+        let timing: Timing = timing.parse().unwrap();
+        let memory =
+            Memory::new(&assemble("set y, 3\nloop:\naddx 2\nsub y, 1\njnz y, loop").unwrap());
+        let mut debugger = Debugger::new(&memory, timing.clone());
+        assert_eq!(debugger.run(), Stop::Halted);
+
+        let mut log = Log::default();
+        let mut cpu = Cpu::with_timing(timing);
+        let cycles = execute_with(&mut cpu, &memory, &mut [&mut log]);
+        assert_eq!(debugger.cycles(), cycles);
+        assert_eq!(debugger.trace(), log.x_after());
+        assert_eq!(debugger.registers().x, 7);
+
+        // rewinding keeps the timing
+        debugger.rewind(0).unwrap();
+        debugger.run();
+        assert_eq!(debugger.trace(), log.x_after());
+    }
+
+    #[rstest]
+    fn test_breakpoints(memory: Memory) {
+        let mut debugger = Debugger::new(&memory, Timing::default());
+        debugger.add_breakpoint(Breakpoint::Cycle(4));
+        assert_eq!(debugger.run(), Stop::Breakpoint(Breakpoint::Cycle(4)));
+        assert_eq!(debugger.cycles(), 3);
+
+        debugger.clear();
+        debugger.rewind(0).unwrap();
+        debugger.add_breakpoint(Breakpoint::ProgramCounter(2));
+        assert_eq!(
+            debugger.run(),
+            Stop::Breakpoint(Breakpoint::ProgramCounter(2))
+        );
+        assert_eq!(debugger.cycles(), 3);
+    }
+
+    #[rstest]
+    fn test_watches(memory: Memory) {
+        let mut debugger = Debugger::new(&memory, Timing::default());
+        debugger.add_watch(Watch::Below(0));
+        assert_eq!(debugger.run(), Stop::Watch(Watch::Below(0)));
+        assert_eq!((debugger.cycles(), debugger.registers().x), (5, -1));
+
+        debugger.rewind(0).unwrap();
+        debugger.clear();
+        debugger.add_watch("changes".parse().unwrap());
+        assert_eq!(debugger.run(), Stop::Watch(Watch::Changes));
+        assert_eq!(debugger.cycles(), 3);
+
+        assert_eq!("== 4".parse::<Watch>().unwrap(), Watch::Equals(4));
+        assert!("= 4".parse::<Watch>().is_err());
+    }
+
+    #[rstest]
+    fn test_rewind_and_x_during(memory: Memory) {
+        let mut debugger = Debugger::new(&memory, Timing::default());
+        debugger.run();
+        assert_eq!(debugger.x_during(1), Some(1));
+        assert_eq!(debugger.x_during(4), Some(4));
+        assert_eq!(debugger.x_during(5), Some(4));
+        assert_eq!(debugger.x_during(6), None);
+        assert_eq!(debugger.x_during(0), None);
+
+        debugger.rewind(2).unwrap();
+        assert_eq!(debugger.trace(), &[1, 1]);
+        assert!(!debugger.is_halted());
+        assert!(debugger.rewind(3).is_err());
+    }
+
+    #[rstest]
+    fn test_interact(memory: Memory) {
+        let mut debugger = Debugger::new(&memory, Timing::default());
+        let mut output = vec![];
+        let commands = "b 4\nc\n\nx 4\nr 1\ns 2\nw < 0\nc\nt\ns x\nfoo\nq\ns\n";
+        interact(&mut debugger, commands.as_bytes(), &mut output).unwrap();
+        assert_eq!(debugger.cycles(), 5);
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            "3 instructions
+[cycle 0] pc 0, x 1, y 0, z 0
+stopped: breakpoint at cycle 4
+[cycle 3] pc 2, x 4, y 0, z 0
+[cycle 4] pc 3, x 4, y 0, z 0, 1 steps pending, next Write(X, Add, Value(-5))
+during cycle 4: x 4
+[cycle 1] pc 1, x 1, y 0, z 0
+[cycle 3] pc 2, x 4, y 0, z 0
+stopped: watch x Below(0)
+[cycle 5] pc 3, x -1, y 0, z 0
+end of cycle 1: x 1
+end of cycle 2: x 1
+end of cycle 3: x 4
+end of cycle 4: x 4
+end of cycle 5: x -1
+s needs a number, not \"x\"
+unknown command: foo
+"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+// Pictures of the CRT, as PNG, PPM or SVG, with each CRT pixel drawn as a
+// square `scale` image pixels across.
+//
+// A frame can also show what happens during a cycle: the pixel the beam is
+// drawing, in red, and the sprite window around X on that row, in yellow.
+// Recording a frame per cycle shows whether the sprite lines up with the
+// beam when it should.
+
+use crate::crt::{sprite_covers, Crt, HEIGHT, WIDTH};
+use crate::observer::{CycleState, Observer};
+use color_eyre::eyre::{eyre, Report, Result};
+use std::path::Path;
+use std::str::FromStr;
+use svg::node::element::Rectangle;
+use svg::Document;
+
+type Rgb = [u8; 3];
+
+const DARK: Rgb = [20, 20, 20];
+const LIT: Rgb = [80, 220, 100];
+const SPRITE: Rgb = [90, 80, 20];
+const SPRITE_LIT: Rgb = [200, 230, 120];
+const BEAM: Rgb = [220, 40, 40];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Ppm,
+    Svg,
+}
+
+// "png", "ppm" or "svg"
+impl FromStr for Format {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "png" => Ok(Format::Png),
+            "ppm" => Ok(Format::Ppm),
+            "svg" => Ok(Format::Svg),
+            _ => Err(eyre!("Invalid image format {s}")),
+        }
+    }
+}
+
+impl Format {
+    // Worked out from a file's extension
+    pub fn of(path: &Path) -> Result<Self> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .ok_or_else(|| eyre!("{} has no extension", path.display()))?
+            .parse()
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Ppm => "ppm",
+            Format::Svg => "svg",
+        }
+    }
+}
+
+// The pixel being drawn during a cycle, and X at the time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Beam {
+    pub row: usize,
+    pub column: usize,
+    pub x: i32,
+}
+
+impl Beam {
+    fn sprite_covers(&self, row: usize, column: usize) -> bool {
+        row == self.row && sprite_covers(self.x, column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub crt: Crt,
+    pub beam: Option<Beam>,
+}
+
+// Records the screen as it stands at the end of each cycle, showing the
+// beam and sprite during it
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    crt: Crt,
+    pub frames: Vec<Frame>,
+}
+
+impl Observer for Recorder {
+    fn during(&mut self, state: &CycleState) {
+        self.crt.during(state);
+
+        let position = (state.cycle - 1) % (WIDTH * HEIGHT);
+        let beam = Beam {
+            row: position / WIDTH,
+            column: position % WIDTH,
+            x: state.x,
+        };
+        self.frames.push(Frame {
+            crt: self.crt.clone(),
+            beam: Some(beam),
+        });
+    }
+}
+
+impl Frame {
+    // Just the screen
+    pub fn still(crt: Crt) -> Self {
+        Self { crt, beam: None }
+    }
+
+    fn colour(&self, row: usize, column: usize) -> Rgb {
+        let lit = self.crt.lit(row, column);
+        match self.beam {
+            Some(beam) if (beam.row, beam.column) == (row, column) => BEAM,
+            Some(beam) if beam.sprite_covers(row, column) => {
+                if lit {
+                    SPRITE_LIT
+                } else {
+                    SPRITE
+                }
+            }
+            _ if lit => LIT,
+            _ => DARK,
+        }
+    }
+
+    // Image width and height, and RGB bytes row by row
+    fn raster(&self, scale: usize) -> (usize, usize, Vec<u8>) {
+        let (width, height) = (WIDTH * scale, HEIGHT * scale);
+        let mut bytes = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for x in 0..width {
+                bytes.extend(self.colour(y / scale, x / scale));
+            }
+        }
+        (width, height, bytes)
+    }
+
+    pub fn to_ppm(&self, scale: usize) -> Vec<u8> {
+        let (width, height, bytes) = self.raster(scale);
+        let mut ppm = format!("P6\n{width} {height}\n255\n").into_bytes();
+        ppm.extend(bytes);
+        ppm
+    }
+
+    pub fn to_png(&self, scale: usize) -> Result<Vec<u8>> {
+        let (width, height, bytes) = self.raster(scale);
+        let mut png = vec![];
+        let mut encoder = png::Encoder::new(&mut png, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&bytes)?;
+        Ok(png)
+    }
+
+    pub fn to_svg(&self, scale: usize) -> String {
+        let fill = |[r, g, b]: Rgb| format!("rgb({r},{g},{b})");
+        let square = |row: usize, column: usize, colour: Rgb| {
+            Rectangle::new()
+                .set("x", column * scale)
+                .set("y", row * scale)
+                .set("width", scale)
+                .set("height", scale)
+                .set("fill", fill(colour))
+        };
+
+        let (width, height) = (WIDTH * scale, HEIGHT * scale);
+        let mut document = Document::new()
+            .set("viewBox", (0, 0, width, height))
+            .set("width", width)
+            .set("height", height)
+            .add(
+                Rectangle::new()
+                    .set("width", width)
+                    .set("height", height)
+                    .set("fill", fill(DARK)),
+            );
+        for row in 0..HEIGHT {
+            for column in 0..WIDTH {
+                let colour = self.colour(row, column);
+                if colour != DARK {
+                    document = document.add(square(row, column, colour));
+                }
+            }
+        }
+        document.to_string()
+    }
+
+    pub fn encode(&self, format: Format, scale: usize) -> Result<Vec<u8>> {
+        match format {
+            Format::Png => self.to_png(scale),
+            Format::Ppm => Ok(self.to_ppm(scale)),
+            Format::Svg => Ok(self.to_svg(scale).into_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::{execute_with, Cpu, Memory};
+
+    // the small example from the puzzle
+    fn frames() -> Vec<Frame> {
+        let memory = Memory::new(&assemble("noop\naddx 3\naddx -5").unwrap());
+        let mut recorder = Recorder::default();
+        execute_with(&mut Cpu::new(), &memory, &mut [&mut recorder]);
+        recorder.frames
+    }
+
+    #[test]
+    fn test_frames() {
+        let frames = frames();
+        assert_eq!(frames.len(), 5);
+        assert_eq!(
+            frames[3].beam,
+            Some(Beam {
+                row: 0,
+                column: 3,
+                x: 4
+            })
+        );
+        assert!(frames[3].crt.lit(0, 3));
+        assert!(!frames[2].crt.lit(0, 3));
+    }
+
+    #[test]
+    fn test_extreme_x() {
+        let memory =
+            Memory::new(&assemble("set x, 2147483647\nnoop\nset x, -2147483648\nnoop").unwrap());
+        let mut recorder = Recorder::default();
+        execute_with(&mut Cpu::new(), &memory, &mut [&mut recorder]);
+        assert_eq!(recorder.frames.len(), 4);
+        // x is only out of range once the first set has run
+        for frame in &recorder.frames[1..] {
+            assert!((0..WIDTH).all(|column| frame.colour(0, column) != SPRITE));
+        }
+        assert_eq!(recorder.frames[1].colour(0, 1), BEAM);
+    }
+
+    #[test]
+    fn test_colour() {
+        let frame = &frames()[3];
+        assert_eq!(frame.colour(0, 2), LIT);
+        assert_eq!(frame.colour(0, 6), DARK);
+        assert_eq!(frame.colour(0, 3), BEAM);
+        assert_eq!(frame.colour(0, 4), SPRITE);
+        assert_eq!(frame.colour(1, 4), DARK);
+
+        let still = Frame::still(frame.crt.clone());
+        assert_eq!(still.colour(0, 3), LIT);
+    }
+
+    #[test]
+    fn test_ppm() {
+        let ppm = frames()[0].to_ppm(2);
+        let header = b"P6\n80 12\n255\n";
+        assert!(ppm.starts_with(header));
+        assert_eq!(ppm.len(), header.len() + 80 * 12 * 3);
+        // the beam is in the top left corner
+        assert_eq!(ppm[header.len()..header.len() + 3], BEAM);
+    }
+
+    #[test]
+    fn test_png() {
+        let png = frames()[1].to_png(3).unwrap();
+        let decoder = png::Decoder::new(png.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut bytes = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut bytes).unwrap();
+        assert_eq!((info.width, info.height), (120, 18));
+        // x is 1, so the sprite covers the lit pixel to the left of the beam
+        assert_eq!(bytes[..3], SPRITE_LIT);
+        assert_eq!(bytes[3 * 3..3 * 3 + 3], BEAM);
+    }
+
+    #[test]
+    fn test_svg() {
+        let crt = frames().pop().unwrap().crt;
+        let svg = Frame::still(crt).to_svg(4);
+        assert!(svg.contains(r#"viewBox="0 0 160 24""#));
+        // background and the five lit pixels
+        assert_eq!(svg.matches("<rect").count(), 6);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(Format::of(Path::new("out/crt.png")).unwrap(), Format::Png);
+        assert_eq!("svg".parse::<Format>().unwrap(), Format::Svg);
+        assert!(Format::of(Path::new("crt")).is_err());
+        assert!(Format::of(Path::new("crt.gif")).is_err());
+    }
+}
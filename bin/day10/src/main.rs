@@ -1,11 +1,28 @@
-use color_eyre::eyre::Result;
-use common::select_and_solve;
-use common::stack::Stack;
+mod asm;
+mod crt;
+mod debugger;
+mod image;
+mod observer;
+mod ocr;
+mod pipeline;
+
+use crate::asm::assemble;
+use crate::crt::Crt;
+use crate::debugger::{interact, Debugger};
+use crate::image::{Format, Frame, Recorder};
+use crate::observer::{CycleState, Observer, SignalStrength};
+use crate::pipeline::{MicroOp, Timing};
+use color_eyre::eyre::{eyre, Result};
+use common::deque::Deque;
+use common::parse::{ParseResult, Span};
+use common::{load_file, select_and_solve};
 use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::combinator::{all_consuming, map, value};
-use nom::sequence::preceded;
-use nom::IResult;
+use nom::character::complete::char;
+use nom::combinator::{map, value};
+use nom::sequence::{preceded, separated_pair};
+use std::path::Path;
+use std::{env, fs, io};
 use tracing::debug;
 
 fn main() -> Result<()> {
@@ -16,6 +33,38 @@ fn main() -> Result<()> {
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
 
+    // day10 run <program> [timing]
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("run") {
+        let filename = args.get(2).ok_or(eyre!("which program should be run?"))?;
+        let timing = args.get(3).map_or(Ok(Timing::default()), |s| s.parse())?;
+        return run(&load_file(filename)?, timing);
+    }
+
+    // day10 debug [program] [timing]
+    if args.get(1).map(String::as_str) == Some("debug") {
+        let filename = args.get(2).map_or("inputs/day10.1", String::as_str);
+        let timing = args.get(3).map_or(Ok(Timing::default()), |s| s.parse())?;
+        return debug(&load_file(filename)?, timing);
+    }
+
+    // day10 image <file.png|file.ppm|file.svg> [scale] [program]
+    if args.get(1).map(String::as_str) == Some("image") {
+        let output = args.get(2).ok_or(eyre!("where should the image go?"))?;
+        let scale = args.get(3).map_or(Ok(8), |s| s.parse())?;
+        let filename = args.get(4).map_or("inputs/day10.1", String::as_str);
+        return export_image(&load_file(filename)?, Path::new(output), scale);
+    }
+
+    // day10 frames <directory> <png|ppm|svg> [scale] [program]
+    if args.get(1).map(String::as_str) == Some("frames") {
+        let output = args.get(2).ok_or(eyre!("where should the frames go?"))?;
+        let format = args.get(3).map_or(Ok(Format::Png), |s| s.parse())?;
+        let scale = args.get(4).map_or(Ok(8), |s| s.parse())?;
+        let filename = args.get(5).map_or("inputs/day10.1", String::as_str);
+        return export_frames(&load_file(filename)?, Path::new(output), format, scale);
+    }
+
     let name = env!("CARGO_PKG_NAME");
     select_and_solve(
         format!("inputs/{name}.1").as_str(),
@@ -26,14 +75,69 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Register {
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Operand {
+    Register(Register),
+    Value(i32),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Condition {
+    Always,
+    Zero(Register),
+    NotZero(Register),
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 enum Instruction {
     Addx(i32),
     Noop,
+    Set(Register, Operand),
+    Add(Register, Operand),
+    Sub(Register, Operand),
+    Mul(Register, Operand),
+    Jump(Condition, usize), // to an address in instruction memory
+}
+
+// How many cycles each instruction takes by default. See pipeline.rs for
+// what happens in each of them.
+const CYCLES: [(&str, usize); 9] = [
+    ("noop", 1),
+    ("addx", 2),
+    ("set", 1),
+    ("add", 2),
+    ("sub", 2),
+    ("mul", 4),
+    ("jmp", 2),
+    ("jz", 2),
+    ("jnz", 2),
+];
+
+impl Instruction {
+    fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Addx(_) => "addx",
+            Instruction::Noop => "noop",
+            Instruction::Set(..) => "set",
+            Instruction::Add(..) => "add",
+            Instruction::Sub(..) => "sub",
+            Instruction::Mul(..) => "mul",
+            Instruction::Jump(Condition::Always, _) => "jmp",
+            Instruction::Jump(Condition::Zero(_), _) => "jz",
+            Instruction::Jump(Condition::NotZero(_), _) => "jnz",
+        }
+    }
 }
 
 // https://stackoverflow.com/a/74809016/
-fn parse_i32(input: &str) -> IResult<&str, i32> {
+fn parse_i32(input: Span) -> ParseResult<i32> {
     // let (i, number) = map_res(recognize(preceded(opt(tag("-")), digit1)), |s| {
     //     i32::from_str(s)
     // })(input)?;
@@ -42,71 +146,111 @@ fn parse_i32(input: &str) -> IResult<&str, i32> {
     nom::character::complete::i32(input)
 }
 
-fn parse_addx(i: &str) -> IResult<&str, Instruction> {
+fn parse_addx(i: Span) -> ParseResult<Instruction> {
     map(preceded(tag("addx "), parse_i32), Instruction::Addx)(i)
 }
 
-fn parse_noop(i: &str) -> IResult<&str, Instruction> {
+fn parse_noop(i: Span) -> ParseResult<Instruction> {
     //map(tag("noop"), |_| Instruction::Noop)(i)
     value(Instruction::Noop, tag("noop"))(i)
 }
 
-// or we can write the parse function as an associated function:
+fn parse_register(i: Span) -> ParseResult<Register> {
+    alt((
+        value(Register::X, char('x')),
+        value(Register::Y, char('y')),
+        value(Register::Z, char('z')),
+    ))(i)
+}
+
+fn parse_operand(i: Span) -> ParseResult<Operand> {
+    alt((
+        map(parse_register, Operand::Register),
+        map(parse_i32, Operand::Value),
+    ))(i)
+}
+
+// "<mnemonic> <register>, <operand>", e.g. "mul y, x" or "set z, -3"
+fn parse_arithmetic(i: Span) -> ParseResult<Instruction> {
+    let operands = || separated_pair(parse_register, tag(", "), parse_operand);
+    alt((
+        map(preceded(tag("set "), operands()), |(r, o)| {
+            Instruction::Set(r, o)
+        }),
+        map(preceded(tag("add "), operands()), |(r, o)| {
+            Instruction::Add(r, o)
+        }),
+        map(preceded(tag("sub "), operands()), |(r, o)| {
+            Instruction::Sub(r, o)
+        }),
+        map(preceded(tag("mul "), operands()), |(r, o)| {
+            Instruction::Mul(r, o)
+        }),
+    ))(i)
+}
+
+// or we can write the parse function as an associated function.
+// Jumps refer to labels, so they are left to the assembler.
 impl Instruction {
-    fn parse(input: &str) -> IResult<&str, Self> {
-        alt((parse_addx, parse_noop))(input)
+    fn parse(input: Span) -> ParseResult<Self> {
+        alt((parse_addx, parse_noop, parse_arithmetic))(input)
     }
 }
 
 #[allow(dead_code)]
-fn parse_instruction(i: &str) -> IResult<&str, Instruction> {
-    alt((parse_addx, parse_noop))(i)
+fn parse_instruction(i: Span) -> ParseResult<Instruction> {
+    alt((parse_addx, parse_noop, parse_arithmetic))(i)
 }
 
 fn parse_instructions(input: &[String]) -> Result<Vec<Instruction>> {
-    // Lifetime issues if we try to avoid the .unwrap() here, due to
-    // the lifetime of 'l' escaping the closure.
-    // let instructions: Vec<Instruction> = input
-    //     .iter()
-    //     .map(|l| all_consuming(Instruction::parse)(l).finish().unwrap().1)
-    //     .collect();
-    // Ok(instructions)
-
-    // E.g. this does not compile:
-    // let instructions: Result<Vec<Instruction>> = input
-    //     .iter()
-    //     .cloned()
-    //     .map(|l| {
-    //         let (_, instruction) = all_consuming(Instruction::parse)(&l)?;
-    //         Ok(instruction)
-    //     })
-    //     .collect();
-    //
-    // instructions
-
-    // The issue is that the error type of the parser contains the &str,
-    // but if we break this reference by mapping the error to an owned string, it will compile:
+    // Parsing line by line runs into lifetime issues, as the error type of
+    // the parser refers to the line it came from:
     // https://stackoverflow.com/a/73506323/
-    let instructions: Result<Vec<Instruction>> = input
-        .iter()
-        .map(|l| {
-            let (_, instruction) =
-                all_consuming(Instruction::parse)(l).map_err(|e| e.to_owned())?;
-            Ok(instruction)
-        })
-        .collect();
-
-    instructions
+    //
+    // Parsing the whole program at once avoids that, and lets errors
+    // refer to line numbers. The puzzle input is a program without labels
+    // or comments, so the assembler handles it too.
+    assemble(&input.join("\n"))
 }
 
+#[derive(Debug)]
 struct Registers {
     program_counter: usize, // always points to the *next* instruction
     x: i32,
+    y: i32,
+    z: i32,
+}
+
+impl Registers {
+    fn get(&self, register: Register) -> i32 {
+        match register {
+            Register::X => self.x,
+            Register::Y => self.y,
+            Register::Z => self.z,
+        }
+    }
+
+    fn get_mut(&mut self, register: Register) -> &mut i32 {
+        match register {
+            Register::X => &mut self.x,
+            Register::Y => &mut self.y,
+            Register::Z => &mut self.z,
+        }
+    }
+
+    fn value(&self, operand: Operand) -> i32 {
+        match operand {
+            Operand::Register(register) => self.get(register),
+            Operand::Value(value) => value,
+        }
+    }
 }
 
 struct Cpu {
-    steps: Stack<Instruction>,
+    pipeline: Deque<MicroOp>, // what is left of the instruction in flight
+    timing: Timing,
     registers: Registers,
+    in_flight: Option<Instruction>, // the instruction last loaded
 
     // flags
     halted: bool,
@@ -114,28 +258,38 @@ struct Cpu {
 
 impl Cpu {
     fn new() -> Self {
+        Self::with_timing(Timing::default())
+    }
+
+    fn with_timing(timing: Timing) -> Self {
         Self {
-            steps: Stack::new(),
+            pipeline: Deque::new(timing.depth()),
+            timing,
             registers: Registers {
                 program_counter: 0,
                 x: 1,
+                y: 0,
+                z: 0,
             },
+            in_flight: None,
             halted: false,
         }
     }
 
     fn load_instruction(&mut self, instruction: &Instruction) {
         debug!("load {instruction:?}");
-        match instruction {
-            Instruction::Addx(x) => {
-                // 2 cycles
-                self.steps.push(Instruction::Addx(*x));
-                self.steps.push(Instruction::Noop);
-            }
-            Instruction::Noop => {
-                // 1 cycle
-                self.steps.push(*instruction);
-            }
+        self.in_flight = Some(*instruction);
+        self.pipeline
+            .try_extend(self.timing.decode(instruction))
+            .expect("the pipeline is empty when loading");
+    }
+
+    fn state(&self, cycle: usize, in_flight: Option<Instruction>) -> CycleState {
+        CycleState {
+            cycle,
+            program_counter: self.registers.program_counter,
+            x: self.registers.x,
+            in_flight,
         }
     }
 
@@ -145,15 +299,34 @@ impl Cpu {
             return;
         }
 
-        // pop the next step off the internal stack
-        let step = self.steps.pop().unwrap();
-        match step {
-            Instruction::Addx(x) => {
-                self.registers.x += x;
-                debug!("add {x}, x is now {}", self.registers.x);
+        let op = self
+            .pipeline
+            .pop_front()
+            .expect("an instruction is in flight");
+        let registers = &mut self.registers;
+        match op {
+            MicroOp::Stall => {
+                debug!("stall");
             }
-            Instruction::Noop => {
-                debug!("noop");
+            MicroOp::Write(r, alu, o) => {
+                let value = alu.apply(registers.get(r), registers.value(o));
+                *registers.get_mut(r) = value;
+                debug!("{alu:?} {o:?}, {r:?} is now {value}");
+            }
+            MicroOp::Jump(condition, address) => {
+                let taken = match condition {
+                    Condition::Always => true,
+                    Condition::Zero(r) => registers.get(r) == 0,
+                    Condition::NotZero(r) => registers.get(r) != 0,
+                };
+                if taken {
+                    debug!("jump to {address}");
+                    registers.program_counter = address;
+                    let stalls = self.timing.branch_stall;
+                    self.pipeline
+                        .try_extend(std::iter::repeat_n(MicroOp::Stall, stalls))
+                        .expect("the pipeline has room for the branch stall");
+                }
             }
         }
     }
@@ -171,9 +344,10 @@ impl Memory {
     }
 }
 
-fn cycle(cpu: &mut Cpu, memory: &Memory) {
-    // Execute one clock tick
-    if cpu.steps.is_empty() {
+// Load the next instruction if the last one has finished, or halt if there
+// isn't one
+fn fetch(cpu: &mut Cpu, memory: &Memory) {
+    if cpu.pipeline.is_empty() {
         let instruction = memory.instructions.get(cpu.registers.program_counter);
         match instruction {
             Some(i) => {
@@ -185,109 +359,177 @@ fn cycle(cpu: &mut Cpu, memory: &Memory) {
             }
         }
     }
+}
+
+// Run the given cycle, telling the observers about it. The cycle in which
+// the cpu finds nothing left to run only gets as far as its start, and
+// doesn't count. Returns whether the cycle ran.
+fn execute_cycle(
+    cpu: &mut Cpu,
+    memory: &Memory,
+    number: usize,
+    observers: &mut [&mut dyn Observer],
+) -> bool {
+    debug!("cycle {number}");
+
+    let continuing = if cpu.pipeline.is_empty() {
+        None
+    } else {
+        cpu.in_flight
+    };
+    let start = cpu.state(number, continuing);
+    observers.iter_mut().for_each(|o| o.start(&start));
+
+    fetch(cpu, memory);
+    if cpu.halted {
+        return false;
+    }
+    let during = cpu.state(number, cpu.in_flight);
+    observers.iter_mut().for_each(|o| o.during(&during));
 
     cpu.tick();
+    let end = cpu.state(number, cpu.in_flight);
+    observers.iter_mut().for_each(|o| o.end(&end));
+    true
 }
 
-fn execute(cpu: &mut Cpu, memory: &Memory) -> Vec<i32> {
-    let mut trace = vec![];
+// Run until halted, telling the observers about every cycle. Returns the
+// number of cycles run.
+fn execute_with(cpu: &mut Cpu, memory: &Memory, observers: &mut [&mut dyn Observer]) -> usize {
     let mut cycles = 0;
-    while !cpu.halted {
+    while !cpu.halted && execute_cycle(cpu, memory, cycles + 1, observers) {
         cycles += 1;
-        debug!("cycle {cycles}");
-        cycle(cpu, memory);
-        trace.push(cpu.registers.x);
     }
-    trace
+    cycles
 }
 
 fn part1(input: Vec<String>) -> Result<String> {
     let memory = Memory::new(&parse_instructions(&input)?);
     let mut cpu = Cpu::new();
 
-    let trace = execute(&mut cpu, &memory);
+    let mut signal = SignalStrength::at(&[20, 60, 100, 140, 180, 220]);
+    execute_with(&mut cpu, &memory, &mut [&mut signal]);
 
-    for (i, v) in trace.iter().enumerate() {
-        println!("end of cycle {}: x {v}", i + 1);
+    for (cycle, x) in &signal.samples {
+        println!("during cycle {cycle}: x {x}");
     }
 
-    println!("during cycle 20: x {}", trace[20 - 1 - 1]);
-    println!("during cycle 60: x {}", trace[60 - 1 - 1]);
-    println!("during cycle 100: x {}", trace[100 - 1 - 1]);
-    println!("during cycle 140: x {}", trace[140 - 1 - 1]);
-    println!("during cycle 180: x {}", trace[180 - 1 - 1]);
-    println!("during cycle 220: x {}", trace[220 - 1 - 1]);
-
-    let signal_strength = |x: u32| -> i32 { trace[x as usize - 1 - 1] * x as i32 };
-
-    let sum: i32 = [20u32, 60, 100, 140, 180, 220]
-        .into_iter()
-        .map(signal_strength)
-        .sum();
-
-    Ok(sum.to_string())
+    Ok(signal.sum().to_string())
 }
 
 fn part2(input: Vec<String>) -> Result<String> {
     let memory = Memory::new(&parse_instructions(&input)?);
     let mut cpu = Cpu::new();
 
-    let trace = execute(&mut cpu, &memory);
+    let mut crt = Crt::new();
+    execute_with(&mut cpu, &memory, &mut [&mut crt]);
+    print!("{crt}");
 
-    for (i, _v) in trace.iter().enumerate() {
-        let hor_pos = (i % 40) as i32;
+    Ok(ocr::read(&crt)?)
+}
 
-        if hor_pos == 0 {
-            println!();
-        }
+// The screen at the end of the program
+fn export_image(program: &str, output: &Path, scale: usize) -> Result<()> {
+    let memory = Memory::new(&assemble(program)?);
+    let mut crt = Crt::new();
+    execute_with(&mut Cpu::new(), &memory, &mut [&mut crt]);
 
-        let during = if i == 0 { 1 } else { trace[i - 1] };
-        let range = (during - 1)..=(during + 1);
-        if range.contains(&hor_pos) {
-            print!("#");
-        } else {
-            print!(".");
-        }
+    let frame = Frame::still(crt);
+    fs::write(output, frame.encode(Format::of(output)?, scale)?)?;
+    Ok(())
+}
+
+// The screen, beam and sprite during every cycle, one file each
+fn export_frames(program: &str, output: &Path, format: Format, scale: usize) -> Result<()> {
+    let memory = Memory::new(&assemble(program)?);
+    let mut recorder = Recorder::default();
+    execute_with(&mut Cpu::new(), &memory, &mut [&mut recorder]);
+
+    fs::create_dir_all(output)?;
+    for (i, frame) in recorder.frames.iter().enumerate() {
+        let path = output.join(format!("cycle-{:05}.{}", i + 1, format.extension()));
+        fs::write(path, frame.encode(format, scale)?)?;
     }
+    Ok(())
+}
+
+fn debug(program: &str, timing: Timing) -> Result<()> {
+    let memory = Memory::new(&assemble(program)?);
+    let mut debugger = Debugger::new(&memory, timing);
+    interact(&mut debugger, io::stdin().lock(), io::stdout())
+}
+
+// Assemble and run a program, showing the screen and where it finished
+fn run(program: &str, timing: Timing) -> Result<()> {
+    let memory = Memory::new(&assemble(program)?);
+    let mut cpu = Cpu::with_timing(timing);
 
-    Ok("2".to_owned())
+    let mut crt = Crt::new();
+    let cycles = execute_with(&mut cpu, &memory, &mut [&mut crt]);
+    print!("{crt}");
+    println!("{cycles} cycles, {:?}", cpu.registers);
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::observer::Log;
+    use common::parse::parse_prefix;
+    use common::vec_of_strings;
     use rstest::*;
     use test_log::test; // enable tracing during tests, set RUST_LOG=debug
 
     #[test]
     fn test_parse_i32() {
-        assert_eq!(parse_i32("0"), Ok(("", 0)));
-        assert_eq!(parse_i32("-1"), Ok(("", -1)));
-        assert_eq!(parse_i32("45"), Ok(("", 45)));
-        assert_eq!(parse_i32("-123"), Ok(("", -123)));
+        assert_eq!(parse_prefix(parse_i32, "0").unwrap(), ("", 0));
+        assert_eq!(parse_prefix(parse_i32, "-1").unwrap(), ("", -1));
+        assert_eq!(parse_prefix(parse_i32, "45").unwrap(), ("", 45));
+        assert_eq!(parse_prefix(parse_i32, "-123").unwrap(), ("", -123));
     }
 
     #[test]
     fn test_parse_addx() {
-        assert_eq!(parse_addx("addx 0"), Ok(("", Instruction::Addx(0))));
-        assert_eq!(parse_addx("addx -1"), Ok(("", Instruction::Addx(-1))));
-        assert_eq!(parse_addx("addx 45"), Ok(("", Instruction::Addx(45))));
-        assert_eq!(parse_addx("addx -123"), Ok(("", Instruction::Addx(-123))));
+        assert_eq!(
+            parse_prefix(parse_addx, "addx 0").unwrap(),
+            ("", Instruction::Addx(0))
+        );
+        assert_eq!(
+            parse_prefix(parse_addx, "addx -1").unwrap(),
+            ("", Instruction::Addx(-1))
+        );
+        assert_eq!(
+            parse_prefix(parse_addx, "addx 45").unwrap(),
+            ("", Instruction::Addx(45))
+        );
+        assert_eq!(
+            parse_prefix(parse_addx, "addx -123").unwrap(),
+            ("", Instruction::Addx(-123))
+        );
     }
 
     #[test]
     fn test_parse_noop() {
-        assert_eq!(parse_noop("noop"), Ok(("", Instruction::Noop)));
+        assert_eq!(
+            parse_prefix(parse_noop, "noop").unwrap(),
+            ("", Instruction::Noop)
+        );
     }
 
     #[test]
     fn test_parse_instruction() {
-        assert_eq!(parse_instruction("addx 0"), Ok(("", Instruction::Addx(0))));
         assert_eq!(
-            parse_instruction("addx -123"),
-            Ok(("", Instruction::Addx(-123)))
+            parse_prefix(parse_instruction, "addx 0").unwrap(),
+            ("", Instruction::Addx(0))
+        );
+        assert_eq!(
+            parse_prefix(parse_instruction, "addx -123").unwrap(),
+            ("", Instruction::Addx(-123))
+        );
+        assert_eq!(
+            parse_prefix(parse_instruction, "noop").unwrap(),
+            ("", Instruction::Noop)
         );
-        assert_eq!(parse_instruction("noop"), Ok(("", Instruction::Noop)));
     }
 
     #[test]
@@ -316,6 +558,64 @@ noop
         )
     }
 
+    #[test]
+    fn test_parse_instructions_error() {
+        let input = vec_of_strings!["noop", "addx 3", "addy 4", "noop"];
+        let e = parse_instructions(&input).unwrap_err().to_string();
+        assert!(e.contains("bad input at line 3, column 1"), "{e}");
+    }
+
+    #[test]
+    fn test_parse_arithmetic() {
+        assert_eq!(
+            parse_prefix(parse_instruction, "mul y, x").unwrap(),
+            (
+                "",
+                Instruction::Mul(Register::Y, Operand::Register(Register::X))
+            )
+        );
+        assert_eq!(
+            parse_prefix(parse_instruction, "set z, -3").unwrap(),
+            ("", Instruction::Set(Register::Z, Operand::Value(-3)))
+        );
+        assert!(parse_prefix(parse_instruction, "sub w, 1").is_err());
+    }
+
+    #[test]
+    fn test_cycles() {
+        let timing = Timing::default();
+        assert_eq!(timing.cycles(&Instruction::Noop), 1);
+        assert_eq!(timing.cycles(&Instruction::Addx(3)), 2);
+        assert_eq!(
+            timing.cycles(&Instruction::Mul(Register::X, Operand::Value(2))),
+            4
+        );
+        assert_eq!(timing.cycles(&Instruction::Jump(Condition::Always, 0)), 2);
+    }
+
+    #[test]
+    fn test_execute_program() {
+        // x = 2 * 3, by adding 2 three times
+        let program = "
+        set y, 3
+loop:   addx 2      ; x starts at 1
+        sub y, 1
+        jnz y, loop
+        sub x, 1
+";
+        let memory = Memory::new(&assemble(program).unwrap());
+        let mut cpu = Cpu::new();
+        let mut log = Log::default();
+        let cycles = execute_with(&mut cpu, &memory, &mut [&mut log]);
+
+        assert_eq!(cpu.registers.x, 6);
+        assert_eq!(cpu.registers.y, 0);
+        // set, three loops of addx, sub and jnz, and the final sub
+        assert_eq!(cycles, 1 + 3 * (2 + 2 + 2) + 2);
+        // x changes at the end of the second cycle of addx
+        assert_eq!(&log.x_after()[..4], &[1, 1, 3, 3]);
+    }
+
     #[fixture]
     fn input() -> Vec<String> {
         "
@@ -479,6 +779,29 @@ noop
 
     #[rstest]
     fn test_part2(input: Vec<String>) {
-        assert_eq!(part2(input).unwrap(), "2");
+        // the example draws stripes rather than letters
+        let e = part2(input).unwrap_err().to_string();
+        assert_eq!(
+            e,
+            "unknown glyph at letter 0:\n##..\n###.\n####\n####\n####\n####"
+        );
+    }
+
+    #[rstest]
+    fn test_crt(input: Vec<String>) {
+        let memory = Memory::new(&parse_instructions(&input).unwrap());
+        let mut crt = Crt::new();
+        assert_eq!(execute_with(&mut Cpu::new(), &memory, &mut [&mut crt]), 240);
+        assert_eq!(
+            crt.to_string(),
+            "\
+##..##..##..##..##..##..##..##..##..##..
+###...###...###...###...###...###...###.
+####....####....####....####....####....
+#####.....#####.....#####.....#####.....
+######......######......######......####
+#######.......#######.......#######.....
+"
+        );
     }
 }
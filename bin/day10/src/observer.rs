@@ -0,0 +1,146 @@
+// Watching the CPU run, a cycle at a time.
+//
+// execute_with() calls each observer three times per cycle: at the start,
+// before anything has happened; during, once the instruction for the cycle
+// is in flight; and at the end, once it has had its effect. X "during" a
+// cycle is what the puzzle asks about, so observers never need to work out
+// which entry of a trace holds it.
+
+use crate::Instruction;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CycleState {
+    pub cycle: usize, // counting from 1
+    pub program_counter: usize,
+    pub x: i32,
+    pub in_flight: Option<Instruction>, // None at the start of a new instruction
+}
+
+pub trait Observer {
+    fn start(&mut self, _state: &CycleState) {}
+    fn during(&mut self, _state: &CycleState) {}
+    fn end(&mut self, _state: &CycleState) {}
+}
+
+// X during the given cycles, and the total signal strength over them
+#[derive(Debug, Clone, Default)]
+pub struct SignalStrength {
+    cycles: Vec<usize>,
+    pub samples: Vec<(usize, i32)>, // cycle, x
+}
+
+impl SignalStrength {
+    pub fn at(cycles: &[usize]) -> Self {
+        Self {
+            cycles: cycles.to_vec(),
+            samples: vec![],
+        }
+    }
+
+    pub fn sum(&self) -> i32 {
+        self.samples
+            .iter()
+            .map(|&(cycle, x)| cycle as i32 * x)
+            .sum()
+    }
+}
+
+impl Observer for SignalStrength {
+    fn during(&mut self, state: &CycleState) {
+        if self.cycles.contains(&state.cycle) {
+            self.samples.push((state.cycle, state.x));
+        }
+    }
+}
+
+// Everything that happened, in order, for tests
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Start,
+    During,
+    End,
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub struct Log {
+    pub entries: Vec<(Stage, CycleState)>,
+}
+
+#[cfg(test)]
+impl Log {
+    // X at the end of each cycle
+    pub fn x_after(&self) -> Vec<i32> {
+        self.entries
+            .iter()
+            .filter(|(stage, _)| *stage == Stage::End)
+            .map(|(_, state)| state.x)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl Observer for Log {
+    fn start(&mut self, state: &CycleState) {
+        self.entries.push((Stage::Start, *state));
+    }
+
+    fn during(&mut self, state: &CycleState) {
+        self.entries.push((Stage::During, *state));
+    }
+
+    fn end(&mut self, state: &CycleState) {
+        self.entries.push((Stage::End, *state));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::{execute_with, Cpu, Memory};
+
+    fn run(program: &str, observer: &mut dyn Observer) -> usize {
+        let memory = Memory::new(&assemble(program).unwrap());
+        execute_with(&mut Cpu::new(), &memory, &mut [observer])
+    }
+
+    #[test]
+    fn test_stages() {
+        let mut log = Log::default();
+        assert_eq!(run("noop\naddx 3", &mut log), 3);
+
+        let state = |cycle, program_counter, x, in_flight| CycleState {
+            cycle,
+            program_counter,
+            x,
+            in_flight,
+        };
+        let addx = Some(Instruction::Addx(3));
+        assert_eq!(
+            log.entries,
+            vec![
+                (Stage::Start, state(1, 0, 1, None)),
+                (Stage::During, state(1, 1, 1, Some(Instruction::Noop))),
+                (Stage::End, state(1, 1, 1, Some(Instruction::Noop))),
+                (Stage::Start, state(2, 1, 1, None)),
+                (Stage::During, state(2, 2, 1, addx)),
+                (Stage::End, state(2, 2, 1, addx)),
+                (Stage::Start, state(3, 2, 1, addx)),
+                (Stage::During, state(3, 2, 1, addx)),
+                (Stage::End, state(3, 2, 4, addx)),
+                // the cpu halts, as there is nothing left to run
+                (Stage::Start, state(4, 2, 4, None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_signal_strength() {
+        let mut signal = SignalStrength::at(&[2, 4, 10]);
+        run("noop\naddx 3\naddx -5", &mut signal);
+        assert_eq!(signal.samples, vec![(2, 1), (4, 4)]);
+        assert_eq!(signal.sum(), 2 + 16);
+    }
+}
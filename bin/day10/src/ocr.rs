@@ -0,0 +1,129 @@
+// Reading letters off the CRT. Each letter is 4 pixels wide and 6 high, with
+// a dark column after it, so the screen holds up to eight of them.
+
+use crate::crt::{Crt, HEIGHT, WIDTH};
+
+const GLYPH_WIDTH: usize = 4;
+const SPACING: usize = GLYPH_WIDTH + 1;
+
+// The letters that turn up in puzzle answers
+const FONT: [(char, [&str; HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("unknown glyph at letter {position}:\n{art}")]
+pub struct UnknownGlyph {
+    pub position: usize, // counting from 0
+    pub art: String,
+}
+
+fn glyph(crt: &Crt, position: usize) -> [String; HEIGHT] {
+    let left = position * SPACING;
+    std::array::from_fn(|row| {
+        (left..left + GLYPH_WIDTH)
+            .map(|column| if crt.lit(row, column) { '#' } else { '.' })
+            .collect()
+    })
+}
+
+// The letters on the screen. A blank space reads as ' '.
+pub fn read(crt: &Crt) -> Result<String, UnknownGlyph> {
+    (0..WIDTH / SPACING)
+        .map(|position| {
+            let rows = glyph(crt, position);
+            if rows.iter().all(|row| !row.contains('#')) {
+                return Ok(' ');
+            }
+            FONT.iter()
+                .find(|(_, pixels)| rows.iter().zip(pixels).all(|(a, b)| a == b))
+                .map(|(letter, _)| *letter)
+                .ok_or_else(|| UnknownGlyph {
+                    position,
+                    art: rows.join("\n"),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every letter in the font, in order, spread over three screens
+    fn screen(letters: &[(char, [&str; HEIGHT])]) -> Crt {
+        let rows: Vec<String> = (0..HEIGHT)
+            .map(|row| {
+                let line: String = letters
+                    .iter()
+                    .map(|(_, pixels)| format!("{}.", pixels[row]))
+                    .collect();
+                format!("{line:.<WIDTH$}")
+            })
+            .collect();
+        rows.join("\n").parse().unwrap()
+    }
+
+    #[test]
+    fn test_read_font() {
+        let mut read_back = String::new();
+        for letters in FONT.chunks(WIDTH / SPACING) {
+            read_back.push_str(read(&screen(letters)).unwrap().trim_end());
+        }
+        let expected: String = FONT.iter().map(|(letter, _)| letter).collect();
+        assert_eq!(read_back, expected);
+    }
+
+    #[test]
+    fn test_read_screen() {
+        let crt: Crt = "\
+###..#....####.####.####.#.....##..####.
+#..#.#....#.......#.#....#....#..#.#....
+#..#.#....###....#..###..#....#....###..
+###..#....#.....#...#....#....#.##.#....
+#.#..#....#....#....#....#....#..#.#....
+#..#.####.####.####.#....####..###.####."
+            .parse()
+            .unwrap();
+        assert_eq!(read(&crt).unwrap(), "RLEZFLGE");
+    }
+
+    #[test]
+    fn test_unknown_glyph() {
+        let crt: Crt = "\
+####.
+#..#.
+#..#.
+#..#.
+#..#.
+####."
+            .lines()
+            .map(|line| format!("{line:.<40}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .parse()
+            .unwrap();
+        let e = read(&crt).unwrap_err();
+        assert_eq!(e.position, 0);
+        assert_eq!(
+            e.to_string(),
+            "unknown glyph at letter 0:\n####\n#..#\n#..#\n#..#\n#..#\n####"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+// How instructions are timed.
+//
+// Each instruction decodes into an ordered list of micro-ops, one per cycle.
+// At most one of them has an effect, and the rest are stalls. How many
+// cycles an instruction takes comes from the Timing, which starts with the
+// cycle table, and where the stalls go depends on its placement:
+//
+//   Latency - stalls first, so the effect lands in the last cycle. This is
+//             the puzzle's rule: addx changes X at the end of its second
+//             cycle.
+//   Stall   - the effect first, then the stalls, as if the CPU had to wait
+//             for something before the next instruction.
+//
+// A taken jump can also stall the CPU for a few more cycles while it
+// refills, as a real pipeline would.
+
+use crate::{Condition, Instruction, Operand, Register, CYCLES};
+use color_eyre::eyre::{eyre, Report, Result};
+use std::iter;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alu {
+    Set,
+    Add,
+    Sub,
+    Mul,
+}
+
+impl Alu {
+    // Registers wrap around rather than overflow
+    pub fn apply(&self, a: i32, b: i32) -> i32 {
+        match self {
+            Alu::Set => b,
+            Alu::Add => a.wrapping_add(b),
+            Alu::Sub => a.wrapping_sub(b),
+            Alu::Mul => a.wrapping_mul(b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MicroOp {
+    Stall,                         // a cycle in which nothing happens
+    Write(Register, Alu, Operand), // register = register <alu> operand
+    Jump(Condition, usize),        // to an address, if the condition holds
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Latency,
+    Stall,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timing {
+    costs: Vec<(&'static str, usize)>, // cycles by mnemonic
+    pub placement: Placement,
+    pub branch_stall: usize, // extra cycles after a jump is taken
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            costs: CYCLES.to_vec(),
+            placement: Placement::Latency,
+            branch_stall: 0,
+        }
+    }
+}
+
+// Comma separated settings on top of the defaults: "latency" or "stall",
+// "branch=N" for the stall after a taken jump, and "<mnemonic>=N" for the
+// cycles an instruction takes, e.g. "stall,mul=6,branch=1"
+impl FromStr for Timing {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut timing = Timing::default();
+        for setting in s.split(',').filter(|setting| !setting.is_empty()) {
+            match setting.split_once('=') {
+                None if setting == "latency" => timing.placement = Placement::Latency,
+                None if setting == "stall" => timing.placement = Placement::Stall,
+                Some(("branch", n)) => timing.branch_stall = n.parse()?,
+                Some((mnemonic, n)) => timing.set_cycles(mnemonic, n.parse()?)?,
+                None => return Err(eyre!("Invalid timing {setting}")),
+            }
+        }
+        Ok(timing)
+    }
+}
+
+impl Timing {
+    pub fn cycles(&self, instruction: &Instruction) -> usize {
+        let mnemonic = instruction.mnemonic();
+        self.costs
+            .iter()
+            .find(|(m, _)| *m == mnemonic)
+            .map(|(_, cycles)| *cycles)
+            .expect("every instruction has a cycle cost")
+    }
+
+    pub fn set_cycles(&mut self, mnemonic: &str, cycles: usize) -> Result<()> {
+        if cycles == 0 {
+            return Err(eyre!("{mnemonic} must take at least one cycle"));
+        }
+        let (_, cost) = self
+            .costs
+            .iter_mut()
+            .find(|(m, _)| *m == mnemonic)
+            .ok_or_else(|| eyre!("unknown instruction {mnemonic}"))?;
+        *cost = cycles;
+        Ok(())
+    }
+
+    // The most micro-ops the pipeline ever holds: the longest instruction,
+    // plus the stall after it if it is a jump that is taken
+    pub fn depth(&self) -> usize {
+        let longest = self.costs.iter().map(|(_, cycles)| *cycles).max();
+        longest.unwrap_or(0) + self.branch_stall
+    }
+
+    pub fn decode(&self, instruction: &Instruction) -> Vec<MicroOp> {
+        let effect = match *instruction {
+            Instruction::Noop => None,
+            Instruction::Addx(x) => Some(MicroOp::Write(Register::X, Alu::Add, Operand::Value(x))),
+            Instruction::Set(r, o) => Some(MicroOp::Write(r, Alu::Set, o)),
+            Instruction::Add(r, o) => Some(MicroOp::Write(r, Alu::Add, o)),
+            Instruction::Sub(r, o) => Some(MicroOp::Write(r, Alu::Sub, o)),
+            Instruction::Mul(r, o) => Some(MicroOp::Write(r, Alu::Mul, o)),
+            Instruction::Jump(condition, address) => Some(MicroOp::Jump(condition, address)),
+        };
+
+        let stalls = iter::repeat_n(
+            MicroOp::Stall,
+            self.cycles(instruction) - effect.iter().count(),
+        );
+        match self.placement {
+            Placement::Latency => stalls.chain(effect).collect(),
+            Placement::Stall => effect.into_iter().chain(stalls).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::observer::Log;
+    use crate::{execute_with, Cpu, Memory};
+
+    fn every_instruction() -> Vec<Instruction> {
+        assemble(
+            "
+start:  noop
+        addx -3
+        set y, 2
+        add z, y
+        sub x, 1
+        mul y, x
+        jmp start
+        jz y, start
+        jnz z, start
+",
+        )
+        .unwrap()
+    }
+
+    fn is_effect(op: &MicroOp) -> bool {
+        *op != MicroOp::Stall
+    }
+
+    #[test]
+    fn test_decode_invariants() {
+        let timings = [
+            Timing::default(),
+            "stall".parse().unwrap(),
+            "mul=7,addx=3,noop=2".parse().unwrap(),
+            "stall,jmp=1,branch=2".parse().unwrap(),
+        ];
+        for timing in &timings {
+            for instruction in every_instruction() {
+                let ops = timing.decode(&instruction);
+                // one micro-op per cycle
+                assert_eq!(ops.len(), timing.cycles(&instruction), "{instruction:?}");
+                // at most one has an effect, and only noop has none
+                let effects = ops.iter().filter(|op| is_effect(op)).count();
+                let expected = usize::from(instruction != Instruction::Noop);
+                assert_eq!(effects, expected, "{instruction:?}");
+                // the effect is at the end for latency, at the start for stall
+                if effects == 1 {
+                    let at = ops.iter().position(is_effect).unwrap();
+                    match timing.placement {
+                        Placement::Latency => assert_eq!(at, ops.len() - 1),
+                        Placement::Stall => assert_eq!(at, 0),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let timing = Timing::default();
+        assert_eq!(timing.decode(&Instruction::Noop), vec![MicroOp::Stall]);
+        assert_eq!(
+            timing.decode(&Instruction::Addx(3)),
+            vec![
+                MicroOp::Stall,
+                MicroOp::Write(Register::X, Alu::Add, Operand::Value(3))
+            ]
+        );
+
+        let timing: Timing = "stall".parse().unwrap();
+        assert_eq!(
+            timing.decode(&Instruction::Jump(Condition::Always, 4)),
+            vec![MicroOp::Jump(Condition::Always, 4), MicroOp::Stall]
+        );
+    }
+
+    #[test]
+    fn test_timing_from_str() {
+        let timing: Timing = "stall,mul=6,branch=1".parse().unwrap();
+        assert_eq!(timing.placement, Placement::Stall);
+        assert_eq!(timing.branch_stall, 1);
+        assert_eq!(
+            timing.cycles(&Instruction::Mul(Register::X, Operand::Value(2))),
+            6
+        );
+        assert_eq!(timing.cycles(&Instruction::Addx(1)), 2);
+
+        assert!("mul=0".parse::<Timing>().is_err());
+        assert!("div=2".parse::<Timing>().is_err());
+        assert!("fast".parse::<Timing>().is_err());
+        assert_eq!("".parse::<Timing>().unwrap(), Timing::default());
+    }
+
+    fn run(program: &str, timing: Timing) -> (usize, Vec<i32>) {
+        let memory = Memory::new(&assemble(program).unwrap());
+        let mut log = Log::default();
+        let cycles = execute_with(&mut Cpu::with_timing(timing), &memory, &mut [&mut log]);
+        (cycles, log.x_after())
+    }
+
+    #[test]
+    fn test_placement() {
+        let program = "addx 3\naddx -5";
+        assert_eq!(run(program, Timing::default()), (4, vec![1, 4, 4, -1]));
+        assert_eq!(
+            run(program, "stall".parse().unwrap()),
+            (4, vec![4, 4, -1, -1])
+        );
+    }
+
+    #[test]
+    fn test_branch_stall() {
+        // the loop body runs twice, and the jump back is taken once
+        let program = "
+        set y, 2
+loop:   sub y, 1
+        jnz y, loop
+        addx 1
+";
+        let (cycles, x) = run(program, Timing::default());
+        assert_eq!(cycles, 1 + 2 * (2 + 2) + 2);
+        assert_eq!(x.last(), Some(&2));
+
+        let (stalled, _) = run(program, "branch=3".parse().unwrap());
+        assert_eq!(stalled, cycles + 3);
+    }
+
+    #[test]
+    fn test_depth() {
+        assert_eq!(Timing::default().depth(), 4);
+        let timing: Timing = "stall,jmp=5,branch=2".parse().unwrap();
+        assert_eq!(timing.depth(), 7);
+    }
+}
@@ -0,0 +1,213 @@
+// Fast-forwarding the simulation, one item at a time.
+//
+// An item's path doesn't depend on any other item: which monkey it goes to
+// next depends only on the monkey holding it and its worry level. With
+// modular arithmetic there are only so many (monkey, worry) states, so the
+// state an item is in at the start of a round must eventually repeat. From
+// then on it goes round the same cycle of rounds for ever, and the
+// inspections over any number of rounds can be counted without simulating
+// them.
+//
+// Within a round an item thrown to a later monkey is inspected again in the
+// same round, so one round can be several inspections.
+
+use crate::parse::{Item, Monkey};
+use crate::simulation::{monkey_business, Relief, Rules};
+use crate::worry::{Arithmetic, Backend, Big, Checked, Modular};
+use color_eyre::eyre::{eyre, Result};
+use std::collections::HashMap;
+
+struct Tracker<'a, A: Arithmetic> {
+    monkeys: &'a [Monkey],
+    arithmetic: &'a A,
+    relief: Relief,
+}
+
+impl<A: Arithmetic> Tracker<'_, A> {
+    // Where the item goes after one inspection
+    fn inspect(&self, monkey: usize, item: &Item<A::Value>) -> Result<(usize, Item<A::Value>)> {
+        let Monkey {
+            operation,
+            divisor,
+            throw_to,
+            ..
+        } = &self.monkeys[monkey];
+        let mut item = item.do_operation(operation, self.arithmetic)?;
+        if let Relief::DivideBy(by) = self.relief {
+            item = item.do_relief(by, self.arithmetic)?;
+        }
+        let to = match item.is_divisible_by(*divisor, self.arithmetic) {
+            true => throw_to.0 .0,
+            false => throw_to.1 .0,
+        };
+        if to >= self.monkeys.len() {
+            return Err(eyre!(
+                "there is no monkey {to}, only {}",
+                self.monkeys.len()
+            ));
+        }
+        Ok((to, item))
+    }
+
+    // The monkeys that inspect the item during a round, and where it is at
+    // the start of the next one
+    fn round(
+        &self,
+        mut monkey: usize,
+        mut worry: A::Value,
+    ) -> Result<(Vec<usize>, usize, A::Value)> {
+        let mut inspected_by = vec![];
+        loop {
+            inspected_by.push(monkey);
+            let (to, item) = self.inspect(monkey, &Item(worry))?;
+            worry = item.0;
+            if to <= monkey {
+                return Ok((inspected_by, to, worry));
+            }
+            monkey = to;
+        }
+    }
+
+    // Add the item's inspections over the given number of rounds to the
+    // counts for each monkey
+    fn follow(
+        &self,
+        monkey: usize,
+        worry: A::Value,
+        rounds: usize,
+        inspections: &mut [u64],
+    ) -> Result<()> {
+        let mut seen: HashMap<(usize, A::Value), usize> = HashMap::new();
+        let mut history: Vec<Vec<usize>> = vec![]; // inspections in each round
+        let mut state = (monkey, worry);
+
+        while history.len() < rounds {
+            if let Some(&start) = seen.get(&state) {
+                // rounds start.. repeat for ever
+                let cycle = &history[start..];
+                let remaining = rounds - history.len();
+                let (repeats, partial) = (remaining / cycle.len(), remaining % cycle.len());
+                for (i, monkeys) in cycle.iter().enumerate() {
+                    let times = repeats as u64 + u64::from(i < partial);
+                    for &monkey in monkeys {
+                        inspections[monkey] += times;
+                    }
+                }
+                break;
+            }
+            seen.insert(state.clone(), history.len());
+
+            let (inspected_by, monkey, worry) = self.round(state.0, state.1.clone())?;
+            for &monkey in &inspected_by {
+                inspections[monkey] += 1;
+            }
+            history.push(inspected_by);
+            state = (monkey, worry);
+        }
+        Ok(())
+    }
+}
+
+// How many items each monkey inspects over the given number of rounds
+pub fn inspections<A: Arithmetic>(
+    monkeys: &[Monkey],
+    arithmetic: &A,
+    relief: Relief,
+    rounds: usize,
+) -> Result<Vec<u64>> {
+    let tracker = Tracker {
+        monkeys,
+        arithmetic,
+        relief,
+    };
+    let mut inspections = vec![0; monkeys.len()];
+    for (i, monkey) in monkeys.iter().enumerate() {
+        for item in &monkey.items {
+            tracker.follow(i, arithmetic.value(item.0), rounds, &mut inspections)?;
+        }
+    }
+    Ok(inspections)
+}
+
+impl Rules {
+    // As monkey_business(), without simulating every round. Only modular
+    // arithmetic is sure to find a cycle quickly.
+    pub fn fast_forward(&self, monkeys: &[Monkey]) -> Result<u64> {
+        let inspections = match self.backend {
+            Backend::Checked => inspections(monkeys, &Checked, self.relief, self.rounds)?,
+            Backend::Modular => {
+                let modular = Modular::for_divisors(monkeys.iter().map(|m| m.divisor))?;
+                inspections(monkeys, &modular, self.relief, self.rounds)?
+            }
+            Backend::Big => inspections(monkeys, &Big, self.relief, self.rounds)?,
+        };
+        monkey_business(&inspections)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::load_all_monkeys;
+    use crate::simulation::{Silent, PART1, PART2};
+    use crate::tests::input;
+    use rstest::*;
+
+    #[rstest]
+    fn test_inspections(input: &str) {
+        let monkeys = load_all_monkeys(input).unwrap();
+        let modular = Modular::for_divisors(monkeys.iter().map(|m| m.divisor)).unwrap();
+        // from the puzzle
+        for (rounds, expected) in [
+            (1, [2, 4, 3, 6]),
+            (20, [99, 97, 8, 103]),
+            (1000, [5204, 4792, 199, 5192]),
+            (10000, [52166, 47830, 1938, 52013]),
+        ] {
+            let counts = inspections(&monkeys, &modular, Relief::None, rounds).unwrap();
+            assert_eq!(counts, expected, "after {rounds} rounds");
+        }
+    }
+
+    #[rstest]
+    #[case(PART1)]
+    #[case(PART2)]
+    #[case(Rules { rounds: 0, ..PART2 })]
+    #[case(Rules { rounds: 7, ..PART2 })]
+    #[case(Rules { rounds: 4321, ..PART2 })]
+    #[case(Rules { rounds: 50, relief: Relief::DivideBy(2), ..PART1 })]
+    fn test_matches_simulation(input: &str, #[case] rules: Rules) {
+        let monkeys = load_all_monkeys(input).unwrap();
+        let naive = rules.monkey_business(&monkeys, &mut Silent);
+        let fast = rules.fast_forward(&monkeys);
+        match naive {
+            Ok(naive) => assert_eq!(fast.unwrap(), naive),
+            Err(_) => assert!(fast.is_err()),
+        }
+    }
+
+    #[rstest]
+    fn test_millions_of_rounds(input: &str) {
+        let monkeys = load_all_monkeys(input).unwrap();
+        let rules = Rules {
+            rounds: 5_000_000,
+            ..PART2
+        };
+        let modular = Modular::for_divisors(monkeys.iter().map(|m| m.divisor)).unwrap();
+        let counts = inspections(&monkeys, &modular, rules.relief, rules.rounds).unwrap();
+        // every item is inspected at least once a round
+        assert!(counts.iter().sum::<u64>() >= 10 * 5_000_000);
+        assert!(rules.fast_forward(&monkeys).is_ok());
+    }
+
+    #[rstest]
+    fn test_overflow(input: &str) {
+        let monkeys = load_all_monkeys(input).unwrap();
+        let rules = Rules {
+            rounds: 20,
+            backend: Backend::Checked,
+            ..PART2
+        };
+        assert!(rules.fast_forward(&monkeys).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+// The right-hand side of a monkey's operation, `new = ...`, as an expression
+// tree over `old` and constants, with the usual precedence:
+//
+//   expr   := term (('+' | '-') term)*
+//   term   := factor (('*' | '/' | '%') factor)*
+//   factor := 'old' | number | '(' expr ')'
+//
+// Evaluation is checked, so an expression that would overflow, go below
+// zero or divide by zero is an error rather than a wrong answer. See
+// worry.rs for the arithmetic it can be evaluated with.
+
+use crate::worry::Arithmetic;
+use color_eyre::eyre::{Report, Result};
+use common::parse::{parse_all, Span};
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{char, one_of, space0};
+use nom::combinator::{cut, map, value};
+use nom::error::ParseError;
+use nom::multi::fold_many0;
+use nom::sequence::{delimited, pair, preceded, terminated};
+use nom::IResult;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+    Old,
+    Const(u64),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    #[error("{a} {op} {b} is out of range")]
+    Overflow { op: BinOp, a: String, b: String },
+
+    #[error("{a} {op} 0 divides by zero")]
+    DivisionByZero { op: BinOp, a: String },
+
+    #[error("{op} can't be done modulo {modulus}")]
+    NotModular { op: BinOp, modulus: u64 },
+}
+
+impl BinOp {
+    fn symbol(&self) -> char {
+        match self {
+            BinOp::Add => '+',
+            BinOp::Sub => '-',
+            BinOp::Mul => '*',
+            BinOp::Div => '/',
+            BinOp::Rem => '%',
+        }
+    }
+
+    fn from_symbol(symbol: char) -> Self {
+        match symbol {
+            '+' => BinOp::Add,
+            '-' => BinOp::Sub,
+            '*' => BinOp::Mul,
+            '/' => BinOp::Div,
+            '%' => BinOp::Rem,
+            _ => unreachable!("not an operator: {symbol}"),
+        }
+    }
+
+    // Higher binds tighter
+    fn precedence(&self) -> u8 {
+        match self {
+            BinOp::Add | BinOp::Sub => 1,
+            BinOp::Mul | BinOp::Div | BinOp::Rem => 2,
+        }
+    }
+
+    pub fn apply(&self, a: u64, b: u64) -> Result<u64, EvalError> {
+        let result = match self {
+            BinOp::Add => a.checked_add(b),
+            BinOp::Sub => a.checked_sub(b),
+            BinOp::Mul => a.checked_mul(b),
+            BinOp::Div | BinOp::Rem if b == 0 => {
+                return Err(EvalError::DivisionByZero {
+                    op: *self,
+                    a: a.to_string(),
+                })
+            }
+            BinOp::Div => a.checked_div(b),
+            BinOp::Rem => a.checked_rem(b),
+        };
+        result.ok_or(EvalError::Overflow {
+            op: *self,
+            a: a.to_string(),
+            b: b.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+impl Expr {
+    pub fn binary(op: BinOp, left: Expr, right: Expr) -> Self {
+        Expr::Binary(op, Box::new(left), Box::new(right))
+    }
+
+    pub fn eval_with<A: Arithmetic>(
+        &self,
+        arithmetic: &A,
+        old: &A::Value,
+    ) -> Result<A::Value, EvalError> {
+        match self {
+            Expr::Old => Ok(old.clone()),
+            Expr::Const(value) => Ok(arithmetic.value(*value)),
+            Expr::Binary(op, left, right) => arithmetic.apply(
+                *op,
+                &left.eval_with(arithmetic, old)?,
+                &right.eval_with(arithmetic, old)?,
+            ),
+        }
+    }
+
+    // Write a sub-expression, in parentheses if it would otherwise bind to
+    // the wrong operator
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, parent: u8, right: bool) -> fmt::Result {
+        match self {
+            Expr::Binary(op, _, _)
+                if op.precedence() < parent || (right && op.precedence() == parent) =>
+            {
+                write!(f, "({self})")
+            }
+            _ => write!(f, "{self}"),
+        }
+    }
+}
+
+// As it would be written in the input, with only the parentheses it needs
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Old => write!(f, "old"),
+            Expr::Const(value) => write!(f, "{value}"),
+            Expr::Binary(op, left, right) => {
+                left.fmt_operand(f, op.precedence(), false)?;
+                write!(f, " {op} ")?;
+                right.fmt_operand(f, op.precedence(), true)
+            }
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_all(parse_expr, s)
+    }
+}
+
+fn token<'a, O, E, F>(parser: F) -> impl FnMut(Span<'a>) -> IResult<Span<'a>, O, E>
+where
+    E: ParseError<Span<'a>>,
+    F: FnMut(Span<'a>) -> IResult<Span<'a>, O, E>,
+{
+    terminated(parser, space0)
+}
+
+fn parse_factor<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, Expr, E> {
+    alt((
+        value(Expr::Old, token(tag("old"))),
+        map(token(nom::character::complete::u64), Expr::Const),
+        delimited(token(char('(')), cut(parse_expr), cut(token(char(')')))),
+    ))(i)
+}
+
+// One precedence level: operands separated by any of the given operators,
+// grouped to the left
+fn parse_level<'a, E, F>(
+    operators: &'static str,
+    mut operand: F,
+) -> impl FnMut(Span<'a>) -> IResult<Span<'a>, Expr, E>
+where
+    E: ParseError<Span<'a>>,
+    F: FnMut(Span<'a>) -> IResult<Span<'a>, Expr, E> + Copy,
+{
+    move |i| {
+        let (i, first) = operand(i)?;
+        fold_many0(
+            pair(token(one_of(operators)), cut(operand)),
+            move || first.clone(),
+            |left, (symbol, right)| Expr::binary(BinOp::from_symbol(symbol), left, right),
+        )(i)
+    }
+}
+
+fn parse_term<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, Expr, E> {
+    parse_level("*/%", parse_factor)(i)
+}
+
+pub fn parse_expr<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, Expr, E> {
+    preceded(space0, parse_level("+-", parse_term))(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worry::Checked;
+    use rstest::*;
+
+    fn expr(s: &str) -> Expr {
+        s.parse().unwrap()
+    }
+
+    impl Expr {
+        fn eval(&self, old: u64) -> Result<u64, EvalError> {
+            self.eval_with(&Checked, &old)
+        }
+    }
+
+    #[test]
+    fn test_parse_puzzle_operations() {
+        assert_eq!(
+            expr("old * 19"),
+            Expr::binary(BinOp::Mul, Expr::Old, Expr::Const(19))
+        );
+        assert_eq!(
+            expr("old + 6"),
+            Expr::binary(BinOp::Add, Expr::Old, Expr::Const(6))
+        );
+        assert_eq!(
+            expr("old * old"),
+            Expr::binary(BinOp::Mul, Expr::Old, Expr::Old)
+        );
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(
+            expr("1 + old * 2"),
+            Expr::binary(
+                BinOp::Add,
+                Expr::Const(1),
+                Expr::binary(BinOp::Mul, Expr::Old, Expr::Const(2))
+            )
+        );
+        assert_eq!(
+            expr("old - 2 - 1"),
+            Expr::binary(
+                BinOp::Sub,
+                Expr::binary(BinOp::Sub, Expr::Old, Expr::Const(2)),
+                Expr::Const(1)
+            )
+        );
+    }
+
+    #[rstest]
+    #[case("old", 7)]
+    #[case("old + 6", 13)]
+    #[case("old * old", 49)]
+    #[case("(old + 1) * 2", 16)]
+    #[case("old + 1 * 2", 9)]
+    #[case("100 / (old - 2) % 3", 2)]
+    #[case("old - 2 - 1", 4)]
+    #[case("old-(2-1)", 6)]
+    #[case(" ( ( old ) ) ", 7)]
+    fn test_eval(#[case] s: &str, #[case] expected: u64) {
+        assert_eq!(expr(s).eval(7), Ok(expected));
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        assert_eq!(
+            expr("old * old").eval(u64::MAX),
+            Err(EvalError::Overflow {
+                op: BinOp::Mul,
+                a: u64::MAX.to_string(),
+                b: u64::MAX.to_string()
+            })
+        );
+        assert_eq!(
+            expr("old - 8").eval(7).unwrap_err().to_string(),
+            "7 - 8 is out of range"
+        );
+        assert_eq!(
+            expr("old % (old - 7)").eval(7).unwrap_err().to_string(),
+            "7 % 0 divides by zero"
+        );
+    }
+
+    #[rstest]
+    #[case("old * 19")]
+    #[case("(old + 1) * 2")]
+    #[case("old - (2 - 1)")]
+    #[case("old - 2 - 1")]
+    #[case("old * (old / 3) % 5")]
+    fn test_display_round_trip(#[case] s: &str) {
+        assert_eq!(expr(s).to_string(), s);
+        assert_eq!(expr(&expr(s).to_string()), expr(s));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("old +".parse::<Expr>().is_err());
+        assert!("(old + 1".parse::<Expr>().is_err());
+        assert!("old ^ 2".parse::<Expr>().is_err());
+        assert!("new".parse::<Expr>().is_err());
+    }
+}
@@ -1,11 +1,68 @@
+mod cycles;
+mod expr;
 mod parse;
+mod simulation;
+mod trace;
+mod worry;
 
 use crate::parse::load_all_monkeys;
-use color_eyre::eyre::Result;
+use crate::simulation::{Relief, Rules, Silent, PART1, PART2};
+use crate::trace::Narration;
+use crate::worry::Backend;
+use color_eyre::eyre::{eyre, Result};
 use common::{load_file, select};
+use std::{env, io};
 
 fn main() -> Result<()> {
     color_eyre::install()?;
+
+    // day11 verify [rounds] [input]
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("verify") {
+        let rounds = args.get(2).map_or(Ok(20), |s| s.parse())?;
+        let filename = args.get(3).map_or("inputs/day11.2", String::as_str);
+        return verify(&load_file(filename)?, rounds);
+    }
+
+    // day11 run <checked|modular|big> [rounds] [relief] [input]
+    if args.get(1).map(String::as_str) == Some("run") {
+        let rules = Rules {
+            backend: args.get(2).ok_or(eyre!("which arithmetic?"))?.parse()?,
+            rounds: args.get(3).map_or(Ok(PART2.rounds), |s| s.parse())?,
+            relief: args.get(4).map_or(Ok(Relief::None), |s| s.parse())?,
+        };
+        let filename = args.get(5).map_or("inputs/day11.2", String::as_str);
+        let monkeys = load_all_monkeys(&load_file(filename)?)?;
+        println!("{}", rules.monkey_business(&monkeys, &mut Silent)?);
+        return Ok(());
+    }
+
+    // day11 forward <rounds> [checked|modular|big] [relief] [input]
+    if args.get(1).map(String::as_str) == Some("forward") {
+        let rules = Rules {
+            rounds: args.get(2).ok_or(eyre!("how many rounds?"))?.parse()?,
+            backend: args.get(3).map_or(Ok(Backend::Modular), |s| s.parse())?,
+            relief: args.get(4).map_or(Ok(Relief::None), |s| s.parse())?,
+        };
+        let filename = args.get(5).map_or("inputs/day11.2", String::as_str);
+        let monkeys = load_all_monkeys(&load_file(filename)?)?;
+        println!("{}", rules.fast_forward(&monkeys)?);
+        return Ok(());
+    }
+
+    // day11 narrate [rounds] [input]
+    if args.get(1).map(String::as_str) == Some("narrate") {
+        let rules = Rules {
+            rounds: args.get(2).map_or(Ok(PART1.rounds), |s| s.parse())?,
+            ..PART1
+        };
+        let filename = args.get(3).map_or("inputs/day11.2", String::as_str);
+        let monkeys = load_all_monkeys(&load_file(filename)?)?;
+        let mut narration = Narration::new(io::stdout().lock());
+        println!("{}", rules.monkey_business(&monkeys, &mut narration)?);
+        return Ok(());
+    }
+
     let name = env!("CARGO_PKG_NAME");
     select(
         format!("inputs/{name}.1").as_str(),
@@ -21,68 +78,8 @@ fn part1_load(filename: &str) -> Result<String> {
 }
 
 fn part1(input: &str) -> Result<String> {
-    let mut monkeys = load_all_monkeys(input)?;
-
-    let mut num_inspections = vec![0; monkeys.len()];
-    let num_rounds = 20;
-
-    for round in 1..=num_rounds {
-        println!("Round {round}");
-        //        for monkey in monkeys.iter_mut() {
-        for i in 0..monkeys.len() {
-            // we can't have multiple mutable references to monkeys,
-            // so we will need to make a copy of the items list and then
-            // clear the list later
-
-            println!("Monkey {}:", monkeys[i].id.0);
-
-            let mc;
-            {
-                let monkey = &mut monkeys[i];
-                mc = monkey.clone();
-                num_inspections[i] += mc.items.len() as u32;
-            }
-
-            for item in mc.items.iter().copied() {
-                println!(
-                    "  Monkey inspects an item with a worry level of {}.",
-                    item.0
-                );
-
-                let new_item = item.do_operation(&mc.operation);
-                let new_item = new_item.do_relief();
-
-                let test_result = new_item.is_divisible_by(mc.divisor);
-
-                let throw_to = match test_result {
-                    true => &mc.throw_to.0,
-                    false => &mc.throw_to.1,
-                };
-
-                println!(
-                    "    Item with worry level {} is thrown to monkey {}.",
-                    new_item.0, throw_to.0
-                );
-                monkeys[throw_to.0].items.push_back(new_item);
-            }
-            monkeys[i].items.clear();
-        }
-
-        println!(
-            "After round {}, the monkeys are holding items with these worry levels:",
-            round
-        );
-        for i in 0..monkeys.len() {
-            println!("Monkey {i}: {:?}", monkeys[i].items);
-        }
-    }
-
-    // find the two largest numbers
-    num_inspections.sort();
-    let n = num_inspections.len();
-    let solution = num_inspections[n - 1] * num_inspections[n - 2];
-
-    Ok(solution.to_string())
+    let monkeys = load_all_monkeys(input)?;
+    Ok(PART1.monkey_business(&monkeys, &mut Silent)?.to_string())
 }
 
 fn part2_load(filename: &str) -> Result<String> {
@@ -90,71 +87,35 @@ fn part2_load(filename: &str) -> Result<String> {
 }
 
 fn part2(input: &str) -> Result<String> {
-    let mut monkeys = load_all_monkeys(input)?;
-
-    let mut num_inspections = vec![0; monkeys.len()];
-    let num_rounds = 10000;
-
-    let divisor_product: u64 = monkeys.iter().map(|m| m.divisor).product();
-    println!("divisor product {divisor_product}");
-
-    for round in 1..=num_rounds {
-        println!("Round {round}");
-        for i in 0..monkeys.len() {
-            // we can't have multiple mutable references to monkeys,
-            // so we will need to make a copy of the items list and then
-            // clear the list later
-
-            //println!("Monkey {}:", monkeys[i].id.0);
-
-            let mc;
-            {
-                let monkey = &mut monkeys[i];
-                mc = monkey.clone();
-                num_inspections[i] += mc.items.len() as u32;
-            }
-
-            for item in mc.items.iter().copied() {
-                // println!(
-                //     "  Monkey inspects an item with a worry level of {}.",
-                //     item.0
-                // );
-
-                let item = parse::Item(item.0 % divisor_product);
-                let item = item.do_operation(&mc.operation);
-                //let new_item = new_item.do_relief();
-
-                let test_result = item.is_divisible_by(mc.divisor);
-
-                let throw_to = match test_result {
-                    true => &mc.throw_to.0,
-                    false => &mc.throw_to.1,
-                };
-
-                // println!(
-                //     "    Item with worry level {} is thrown to monkey {}.",
-                //     new_item.0, throw_to.0
-                // );
-                monkeys[throw_to.0].items.push_back(item);
-            }
-            monkeys[i].items.clear();
-        }
+    let monkeys = load_all_monkeys(input)?;
+    Ok(PART2.monkey_business(&monkeys, &mut Silent)?.to_string())
+}
 
-        println!(
-            "After round {}, the monkeys are holding items with these worry levels:",
-            round
-        );
-        for i in 0..monkeys.len() {
-            println!("Monkey {i}: {:?}", monkeys[i].items);
+// Run part 2's rules for a few rounds in every arithmetic. Modular and big
+// must agree, and checked may overflow.
+fn verify(input: &str, rounds: usize) -> Result<()> {
+    let monkeys = load_all_monkeys(input)?;
+    let results = [Backend::Checked, Backend::Modular, Backend::Big].map(|backend| {
+        let rules = Rules {
+            rounds,
+            backend,
+            ..PART2
+        };
+        (backend, rules.monkey_business(&monkeys, &mut Silent))
+    });
+    for (backend, result) in &results {
+        match result {
+            Ok(value) => println!("{backend}: {value}"),
+            Err(e) => println!("{backend}: {e}"),
         }
     }
 
-    // find the two largest numbers
-    num_inspections.sort();
-    let n = num_inspections.len();
-    let solution: u64 = num_inspections[n - 1] as u64 * num_inspections[n - 2] as u64;
-
-    Ok(solution.to_string())
+    match results {
+        [_, (_, Ok(modular)), (_, Ok(big))] if modular == big => Ok(()),
+        _ => Err(eyre!(
+            "modular and big arithmetic disagree after {rounds} rounds"
+        )),
+    }
 }
 
 #[cfg(test)]
@@ -164,7 +125,7 @@ mod tests {
     use rstest::*;
 
     #[fixture]
-    fn input() -> &'static str {
+    pub fn input() -> &'static str {
         "Monkey 0:
   Starting items: 79, 98
   Operation: new = old * 19
@@ -204,4 +165,28 @@ Monkey 3:
     fn test_part2(input: &str) {
         assert_eq!(part2(input).unwrap(), "2713310158");
     }
+
+    #[rstest]
+    fn test_without_modulo_trick(input: &str) {
+        let monkeys = load_all_monkeys(input).unwrap();
+        // inspections after 20 rounds, from the puzzle: 99, 97, 8 and 103
+        for (backend, expected) in [(Backend::Big, 103 * 99), (Backend::Modular, 103 * 99)] {
+            let rules = Rules {
+                rounds: 20,
+                backend,
+                ..PART2
+            };
+            assert_eq!(
+                rules.monkey_business(&monkeys, &mut Silent).unwrap(),
+                expected
+            );
+        }
+        let checked = Rules {
+            rounds: 20,
+            backend: Backend::Checked,
+            ..PART2
+        };
+        assert!(checked.monkey_business(&monkeys, &mut Silent).is_err());
+        verify(input, 20).unwrap();
+    }
 }
@@ -1,84 +1,76 @@
-use color_eyre::eyre::{eyre, Result};
-use miette::GraphicalReportHandler;
-use nom::branch::alt;
+use crate::expr::{parse_expr, BinOp, EvalError, Expr};
+use crate::worry::Arithmetic;
+use color_eyre::eyre::Result;
+use common::parse::{parse_all, Span};
 use nom::bytes::complete::tag;
 use nom::character::complete::{char, newline, space0};
-use nom::combinator::map;
-use nom::error::ParseError;
+use nom::combinator::cut;
+use nom::error::{ErrorKind, FromExternalError, ParseError};
 use nom::multi::separated_list1;
-use nom::sequence::preceded;
 use nom::sequence::tuple;
 use nom::IResult;
-use nom_locate::LocatedSpan;
-use nom_supreme::error::{BaseErrorKind, ErrorTree, GenericErrorTree};
-use nom_supreme::final_parser::final_parser;
 use std::collections::VecDeque;
 
-pub type Span<'a> = LocatedSpan<&'a str>;
-
+// A worry level, in whichever arithmetic the monkeys are using (see worry.rs)
 #[derive(Debug, PartialEq, Clone, Copy)]
-pub struct Item(pub u64);
+pub struct Item<V = u64>(pub V);
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct MonkeyId(pub usize);
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum Operation {
-    Add(u64),
-    Multiply(u64),
-    Square,
-}
-
 #[derive(Debug, PartialEq, Clone)]
 pub struct ThrowTo(pub MonkeyId, pub MonkeyId); // if true, if false
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct Monkey {
+pub struct Monkey<V = u64> {
     pub id: MonkeyId,
-    pub items: VecDeque<Item>,
-    pub operation: Operation,
+    pub items: VecDeque<Item<V>>,
+    pub operation: Expr, // new = ...
     pub divisor: u64,
     pub throw_to: ThrowTo,
 }
 
-impl Item {
-    pub fn do_operation(&self, operation: &Operation) -> Self {
-        match operation {
-            Operation::Add(y) => {
-                let z = self.0 + y;
-                println!("    Worry level is increased by {y} to {z}.");
-                Self(z)
-            }
-            Operation::Multiply(y) => {
-                let z = self.0 * y;
-                println!("    Worry level is multiplied by {y} to {z}.");
-                Self(z)
-            }
-            Operation::Square => {
-                let z = self.0 * self.0;
-                println!("    Worry level is multiplied by itself to {z}.");
-                Self(z)
-            }
+impl Monkey {
+    // The same monkey, holding its items as values of another arithmetic
+    pub fn with_arithmetic<A: Arithmetic>(&self, arithmetic: &A) -> Monkey<A::Value> {
+        Monkey {
+            id: self.id.clone(),
+            items: self
+                .items
+                .iter()
+                .map(|item| Item(arithmetic.value(item.0)))
+                .collect(),
+            operation: self.operation.clone(),
+            divisor: self.divisor,
+            throw_to: self.throw_to.clone(),
         }
     }
+}
 
-    pub fn do_relief(&self) -> Self {
-        let z = self.0 / 3;
-        println!(
-            "    Monkey gets bored with item. Worry level is divided by 3 to {}.",
-            z
-        );
-        Self(z)
+impl<V> Item<V> {
+    pub fn do_operation<A>(&self, operation: &Expr, arithmetic: &A) -> Result<Self, EvalError>
+    where
+        A: Arithmetic<Value = V>,
+    {
+        Ok(Self(operation.eval_with(arithmetic, &self.0)?))
     }
 
-    pub fn is_divisible_by(&self, divisor: u64) -> bool {
-        let divisible = self.0 % divisor == 0;
-        println!(
-            "    Current worry level is {}divisible by {}.",
-            if divisible { "" } else { "not " },
-            divisor
-        );
-        divisible
+    pub fn do_relief<A>(&self, by: u64, arithmetic: &A) -> Result<Self, EvalError>
+    where
+        A: Arithmetic<Value = V>,
+    {
+        Ok(Self(arithmetic.apply(
+            BinOp::Div,
+            &self.0,
+            &arithmetic.value(by),
+        )?))
+    }
+
+    pub fn is_divisible_by<A>(&self, divisor: u64, arithmetic: &A) -> bool
+    where
+        A: Arithmetic<Value = V>,
+    {
+        arithmetic.remainder(&self.0, divisor) == 0
     }
 }
 
@@ -107,42 +99,32 @@ pub fn parse_starting_items<'a, E: ParseError<Span<'a>>>(
     Ok((i, starting_items))
 }
 
-fn parse_operation_add<'a, E: ParseError<Span<'a>>>(
-    i: Span<'a>,
-) -> IResult<Span<'a>, Operation, E> {
-    map(preceded(tag("+ "), nom::character::complete::u64), |x| {
-        Operation::Add(x)
-    })(i)
-}
-
-fn parse_operation_multiply<'a, E: ParseError<Span<'a>>>(
-    i: Span<'a>,
-) -> IResult<Span<'a>, Operation, E> {
-    map(preceded(tag("* "), nom::character::complete::u64), |x| {
-        Operation::Multiply(x)
-    })(i)
-}
-
-fn parse_operation_square<'a, E: ParseError<Span<'a>>>(
-    i: Span<'a>,
-) -> IResult<Span<'a>, Operation, E> {
-    map(tag("* old"), |_| Operation::Square)(i)
-}
-
-pub fn parse_operation<'a, E: ParseError<Span<'a>>>(
-    i: Span<'a>,
-) -> IResult<Span<'a>, Operation, E> {
+pub fn parse_operation<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, Expr, E> {
     let (i, _) = space0(i)?;
-    let (i, _) = tag("Operation: new = old ")(i)?;
-    alt((
-        parse_operation_add,
-        parse_operation_multiply,
-        parse_operation_square,
-    ))(i)
+    let (i, _) = tag("Operation: new =")(i)?;
+    parse_expr(i)
 }
 
-pub fn parse_divisor<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, u64, E> {
-    preceded(tag("  Test: divisible by "), nom::character::complete::u64)(i)
+#[derive(thiserror::Error, Debug)]
+#[error("divisor must be at least 1")]
+pub struct ZeroDivisor;
+
+// Nothing is divisible by 0, so it is rejected here rather than left to
+// fail in the middle of a round
+pub fn parse_divisor<'a, E>(i: Span<'a>) -> IResult<Span<'a>, u64, E>
+where
+    E: ParseError<Span<'a>> + FromExternalError<Span<'a>, ZeroDivisor>,
+{
+    let (i, _) = tag("  Test: divisible by ")(i)?;
+    let (rest, divisor) = cut(nom::character::complete::u64)(i)?;
+    if divisor == 0 {
+        return Err(nom::Err::Failure(E::from_external_error(
+            i,
+            ErrorKind::Verify,
+            ZeroDivisor,
+        )));
+    }
+    Ok((rest, divisor))
 }
 
 pub fn parse_throw_to<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, ThrowTo, E> {
@@ -161,7 +143,10 @@ pub fn parse_throw_to<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<
     ))
 }
 
-pub fn parse_monkey<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a>, Monkey, E> {
+pub fn parse_monkey<'a, E>(i: Span<'a>) -> IResult<Span<'a>, Monkey, E>
+where
+    E: ParseError<Span<'a>> + FromExternalError<Span<'a>, ZeroDivisor>,
+{
     let (i, (id, _, items, _, operation, _, divisor, _, throw_to, _)) = tuple((
         parse_monkey_id,
         newline,
@@ -187,53 +172,16 @@ pub fn parse_monkey<'a, E: ParseError<Span<'a>>>(i: Span<'a>) -> IResult<Span<'a
     ))
 }
 
-pub fn parse_monkeys<'a, E: ParseError<Span<'a>>>(
-    i: Span<'a>,
-) -> IResult<Span<'a>, Vec<Monkey>, E> {
-    separated_list1(newline, parse_monkey)(i)
+pub fn parse_monkeys<'a, E>(i: Span<'a>) -> IResult<Span<'a>, Vec<Monkey>, E>
+where
+    E: ParseError<Span<'a>> + FromExternalError<Span<'a>, ZeroDivisor>,
+{
+    // a blank line means another monkey follows
+    separated_list1(newline, cut(parse_monkey))(i)
 }
 
-#[derive(thiserror::Error, Debug, miette::Diagnostic)]
-#[error("bad input")]
-struct BadInput {
-    #[source_code]
-    //src: &'static str,
-    src: String,
-
-    #[label("{kind}")]
-    bad_bit: miette::SourceSpan,
-
-    kind: BaseErrorKind<&'static str, Box<dyn std::error::Error + Send + Sync>>,
-}
-
-pub fn load_all_monkeys(input_static: &str) -> Result<Vec<Monkey>> {
-    let input = Span::new(input_static);
-    let monkeys_res: Result<_, ErrorTree<Span>> =
-        final_parser(parse_monkeys::<ErrorTree<Span>>)(input);
-    let monkeys = match monkeys_res {
-        Ok(monkeys) => monkeys,
-        Err(e) => {
-            match e {
-                GenericErrorTree::Base { location, kind } => {
-                    let offset = location.location_offset().into();
-                    let err = BadInput {
-                        src: input_static.to_owned(),
-                        bad_bit: miette::SourceSpan::new(offset, 0.into()),
-                        kind,
-                    };
-                    let mut s = String::new();
-                    GraphicalReportHandler::new()
-                        .render_report(&mut s, &err)
-                        .unwrap();
-                    println!("{s}");
-                }
-                GenericErrorTree::Stack { .. } => todo!("stack"),
-                GenericErrorTree::Alt(_) => todo!("alt"),
-            }
-            return Err(eyre!(""));
-        }
-    };
-    Ok(monkeys)
+pub fn load_all_monkeys(input: &str) -> Result<Vec<Monkey>> {
+    parse_all(parse_monkeys, input)
 }
 
 #[cfg(test)]
@@ -241,8 +189,9 @@ mod tests {
     use super::*;
     use crate::parse::{
         parse_divisor, parse_monkey, parse_monkey_id, parse_monkeys, parse_operation,
-        parse_starting_items, parse_throw_to, Item, Monkey, MonkeyId, Operation, ThrowTo,
+        parse_starting_items, parse_throw_to, Item, Monkey, MonkeyId, ThrowTo,
     };
+    use crate::worry::Checked;
     use nom_supreme::error::ErrorTree;
     use pretty_assertions::assert_eq;
     use rstest::*;
@@ -279,19 +228,19 @@ mod tests {
             parse_operation::<ErrorTree<Span>>("  Operation: new = old * 19".into())
                 .unwrap()
                 .1,
-            Operation::Multiply(19)
+            "old * 19".parse().unwrap()
         );
         assert_eq!(
             parse_operation::<ErrorTree<Span>>("  Operation: new = old + 6".into())
                 .unwrap()
                 .1,
-            Operation::Add(6)
+            "old + 6".parse().unwrap()
         );
         assert_eq!(
             parse_operation::<ErrorTree<Span>>("  Operation: new = old * old".into())
                 .unwrap()
                 .1,
-            Operation::Square
+            "old * old".parse().unwrap()
         );
     }
 
@@ -303,6 +252,7 @@ mod tests {
                 .1,
             19
         );
+        assert!(parse_divisor::<ErrorTree<Span>>("  Test: divisible by 0".into()).is_err());
     }
 
     #[test]
@@ -329,7 +279,7 @@ mod tests {
             Monkey {
                 id: MonkeyId(42),
                 items: VecDeque::from([Item(65), Item(78)]),
-                operation: Operation::Multiply(3),
+                operation: "old * 3".parse().unwrap(),
                 divisor: 5,
                 throw_to: ThrowTo(MonkeyId(2), MonkeyId(3)),
             }
@@ -376,28 +326,28 @@ Monkey 3:
                 Monkey {
                     id: MonkeyId(0),
                     items: VecDeque::from([Item(79), Item(98)]),
-                    operation: Operation::Multiply(19),
+                    operation: "old * 19".parse().unwrap(),
                     divisor: 23,
                     throw_to: ThrowTo(MonkeyId(2), MonkeyId(3)),
                 },
                 Monkey {
                     id: MonkeyId(1),
                     items: VecDeque::from([Item(54), Item(65), Item(75), Item(74)]),
-                    operation: Operation::Add(6),
+                    operation: "old + 6".parse().unwrap(),
                     divisor: 19,
                     throw_to: ThrowTo(MonkeyId(2), MonkeyId(0)),
                 },
                 Monkey {
                     id: MonkeyId(2),
                     items: VecDeque::from([Item(79), Item(60), Item(97)]),
-                    operation: Operation::Square,
+                    operation: "old * old".parse().unwrap(),
                     divisor: 13,
                     throw_to: ThrowTo(MonkeyId(1), MonkeyId(3)),
                 },
                 Monkey {
                     id: MonkeyId(3),
                     items: VecDeque::from([Item(74)]),
-                    operation: Operation::Add(3),
+                    operation: "old + 3".parse().unwrap(),
                     divisor: 17,
                     throw_to: ThrowTo(MonkeyId(0), MonkeyId(1)),
                 },
@@ -410,4 +360,33 @@ Monkey 3:
         let monkeys = load_all_monkeys(input).unwrap();
         assert_eq!(monkeys.len(), 4);
     }
+
+    #[rstest]
+    fn test_load_all_monkeys_error(input: &str) {
+        let input = input.replace("new = old + 6", "new = old ^ 6");
+        let e = load_all_monkeys(&input).unwrap_err().to_string();
+        assert!(e.contains("bad input at line 10, column 24"), "{e}");
+    }
+
+    #[rstest]
+    fn test_load_divisor_zero(input: &str) {
+        let input = input.replace("divisible by 19", "divisible by 0");
+        let e = load_all_monkeys(&input).unwrap_err().to_string();
+        assert!(e.contains("bad input at line 11, column 22"), "{e}");
+        assert!(e.contains("divisor must be at least 1"), "{e}");
+    }
+
+    #[rstest]
+    fn test_load_expression_operation(input: &str) {
+        let input = input.replace("new = old + 6", "new = (old + 6) % 1000 - old / 2");
+        let monkeys = load_all_monkeys(&input).unwrap();
+        assert_eq!(
+            monkeys[1].operation.to_string(),
+            "(old + 6) % 1000 - old / 2"
+        );
+        assert_eq!(
+            Item(54).do_operation(&monkeys[1].operation, &Checked),
+            Ok(Item(33))
+        );
+    }
 }
@@ -0,0 +1,321 @@
+// Monkeys passing items around, round after round. The two parts of the
+// puzzle only differ in their Rules: how many rounds, whether the worry
+// level is divided after each inspection, and which arithmetic keeps the
+// worry levels in range (see worry.rs).
+
+use crate::parse::{Item, Monkey};
+use crate::trace::{Event, Trace};
+use crate::worry::{Arithmetic, Backend, Big, Checked, Modular};
+use color_eyre::eyre::{eyre, Report, Result};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relief {
+    None,
+    DivideBy(u64), // after each inspection, rounding down
+}
+
+// "none", or the number the worry level is divided by
+impl FromStr for Relief {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Relief::None),
+            _ => match s.parse() {
+                Ok(0) | Err(_) => Err(eyre!("Invalid relief {s}")),
+                Ok(divisor) => Ok(Relief::DivideBy(divisor)),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    pub rounds: usize,
+    pub relief: Relief,
+    pub backend: Backend,
+}
+
+pub const PART1: Rules = Rules {
+    rounds: 20,
+    relief: Relief::DivideBy(3),
+    backend: Backend::Checked,
+};
+
+pub const PART2: Rules = Rules {
+    rounds: 10000,
+    relief: Relief::None,
+    backend: Backend::Modular,
+};
+
+// Told about the monkeys, and how many items each has inspected so far,
+// after every round, as well as about each step along the way
+pub trait RoundObserver: Trace {
+    fn after_round<V: fmt::Display>(
+        &mut self,
+        _round: usize,
+        _monkeys: &[Monkey<V>],
+        _inspections: &[u64],
+    ) {
+    }
+}
+
+pub struct Silent;
+
+impl Trace for Silent {}
+impl RoundObserver for Silent {}
+
+pub struct Simulation<A: Arithmetic> {
+    arithmetic: A,
+    relief: Relief,
+    monkeys: Vec<Monkey<A::Value>>,
+    inspections: Vec<u64>, // by monkey
+    round: usize,          // rounds completed
+}
+
+impl<A: Arithmetic> Simulation<A> {
+    pub fn new(monkeys: &[Monkey], arithmetic: A, relief: Relief) -> Self {
+        Self {
+            monkeys: monkeys
+                .iter()
+                .map(|monkey| monkey.with_arithmetic(&arithmetic))
+                .collect(),
+            inspections: vec![0; monkeys.len()],
+            arithmetic,
+            relief,
+            round: 0,
+        }
+    }
+
+    // One monkey inspects and throws each of its items in turn
+    fn turn(&mut self, i: usize, trace: &mut impl Trace) -> Result<()> {
+        // the items are taken, rather than the monkey cloned, so that the
+        // other monkeys can be thrown to
+        let items = std::mem::take(&mut self.monkeys[i].items);
+        self.inspections[i] += items.len() as u64;
+        trace.record::<A::Value>(Event::Turn { monkey: i });
+
+        let monkey = &self.monkeys[i];
+        let (operation, divisor) = (monkey.operation.clone(), monkey.divisor);
+        let (if_true, if_false) = (monkey.throw_to.0 .0, monkey.throw_to.1 .0);
+        for item in items {
+            trace.record(Event::Inspect { worry: &item.0 });
+            let mut item = item.do_operation(&operation, &self.arithmetic)?;
+            trace.record(Event::Operation {
+                operation: &operation,
+                worry: &item.0,
+            });
+            if let Relief::DivideBy(by) = self.relief {
+                item = item.do_relief(by, &self.arithmetic)?;
+                trace.record(Event::Relief { by, worry: &item.0 });
+            }
+
+            let divisible = item.is_divisible_by(divisor, &self.arithmetic);
+            trace.record::<A::Value>(Event::Test { divisor, divisible });
+            let to = if divisible { if_true } else { if_false };
+            trace.record(Event::Throw { worry: &item.0, to });
+            self.throw(item, to)?;
+        }
+        Ok(())
+    }
+
+    fn throw(&mut self, item: Item<A::Value>, to: usize) -> Result<()> {
+        let count = self.monkeys.len();
+        let monkey = self
+            .monkeys
+            .get_mut(to)
+            .ok_or_else(|| eyre!("there is no monkey {to}, only {count}"))?;
+        monkey.items.push_back(item);
+        Ok(())
+    }
+
+    pub fn round(&mut self, trace: &mut impl Trace) -> Result<()> {
+        for i in 0..self.monkeys.len() {
+            self.turn(i, trace)?;
+        }
+        self.round += 1;
+        Ok(())
+    }
+
+    pub fn run(&mut self, rounds: usize, observer: &mut impl RoundObserver) -> Result<()> {
+        for _ in 0..rounds {
+            self.round(observer)?;
+            observer.after_round(self.round, &self.monkeys, &self.inspections);
+        }
+        Ok(())
+    }
+
+    pub fn monkey_business(&self) -> Result<u64> {
+        monkey_business(&self.inspections)
+    }
+}
+
+// The number of inspections by the two busiest monkeys, multiplied
+pub fn monkey_business(inspections: &[u64]) -> Result<u64> {
+    let mut inspections = inspections.to_vec();
+    inspections.sort_unstable_by(|a, b| b.cmp(a));
+    match inspections[..] {
+        [first, second, ..] => first
+            .checked_mul(second)
+            .ok_or_else(|| eyre!("{first} * {second} is too much monkey business")),
+        _ => Err(eyre!("monkey business needs at least two monkeys")),
+    }
+}
+
+fn run_with<A: Arithmetic>(
+    monkeys: &[Monkey],
+    arithmetic: A,
+    rules: Rules,
+    observer: &mut impl RoundObserver,
+) -> Result<u64> {
+    let mut simulation = Simulation::new(monkeys, arithmetic, rules.relief);
+    simulation.run(rules.rounds, observer)?;
+    simulation.monkey_business()
+}
+
+impl Rules {
+    pub fn monkey_business(
+        &self,
+        monkeys: &[Monkey],
+        observer: &mut impl RoundObserver,
+    ) -> Result<u64> {
+        match self.backend {
+            Backend::Checked => run_with(monkeys, Checked, *self, observer),
+            Backend::Modular => {
+                let modular = Modular::for_divisors(monkeys.iter().map(|m| m.divisor))?;
+                run_with(monkeys, modular, *self, observer)
+            }
+            Backend::Big => run_with(monkeys, Big, *self, observer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::load_all_monkeys;
+    use crate::tests::input;
+    use rstest::*;
+
+    #[derive(Default)]
+    struct Snapshots {
+        rounds: Vec<(usize, Vec<Vec<String>>)>,
+        inspections: Vec<u64>, // after the last round
+    }
+
+    impl Trace for Snapshots {}
+
+    impl RoundObserver for Snapshots {
+        fn after_round<V: fmt::Display>(
+            &mut self,
+            round: usize,
+            monkeys: &[Monkey<V>],
+            inspections: &[u64],
+        ) {
+            let items = monkeys
+                .iter()
+                .map(|monkey| monkey.items.iter().map(|item| item.0.to_string()).collect())
+                .collect();
+            self.rounds.push((round, items));
+            self.inspections = inspections.to_vec();
+        }
+    }
+
+    fn worry(levels: &[&[u64]]) -> Vec<Vec<String>> {
+        levels
+            .iter()
+            .map(|monkey| monkey.iter().map(u64::to_string).collect())
+            .collect()
+    }
+
+    #[rstest]
+    fn test_part1_rounds(input: &str) {
+        let monkeys = load_all_monkeys(input).unwrap();
+        let mut simulation = Simulation::new(&monkeys, Checked, Relief::DivideBy(3));
+        let mut snapshots = Snapshots::default();
+        simulation.run(20, &mut snapshots).unwrap();
+
+        assert_eq!(snapshots.rounds.len(), 20);
+        assert_eq!(
+            snapshots.rounds[0],
+            (
+                1,
+                worry(&[
+                    &[20, 23, 27, 26],
+                    &[2080, 25, 167, 207, 401, 1046],
+                    &[],
+                    &[]
+                ])
+            )
+        );
+        assert_eq!(
+            snapshots.rounds[19],
+            (
+                20,
+                worry(&[&[10, 12, 14, 26, 34], &[245, 93, 53, 199, 115], &[], &[]])
+            )
+        );
+        assert_eq!(snapshots.inspections, [101, 95, 7, 105]);
+        assert_eq!(simulation.monkey_business().unwrap(), 10605);
+    }
+
+    #[rstest]
+    fn test_rules(input: &str) {
+        let monkeys = load_all_monkeys(input).unwrap();
+        assert_eq!(PART1.monkey_business(&monkeys, &mut Silent).unwrap(), 10605);
+        assert_eq!(
+            PART2.monkey_business(&monkeys, &mut Silent).unwrap(),
+            2713310158
+        );
+
+        // the inspection counts from the puzzle after round 1000
+        let rules = Rules {
+            rounds: 1000,
+            ..PART2
+        };
+        let mut simulation = Simulation::new(&monkeys, Modular::new(96577), rules.relief);
+        let mut snapshots = Snapshots::default();
+        simulation.run(rules.rounds, &mut snapshots).unwrap();
+        assert_eq!(snapshots.inspections, [5204, 4792, 199, 5192]);
+    }
+
+    #[rstest]
+    fn test_relief_needs_division(input: &str) {
+        let monkeys = load_all_monkeys(input).unwrap();
+        let rules = Rules {
+            backend: Backend::Modular,
+            ..PART1
+        };
+        let e = rules.monkey_business(&monkeys, &mut Silent).unwrap_err();
+        assert_eq!(e.to_string(), "/ can't be done modulo 96577");
+    }
+
+    #[rstest]
+    fn test_modulus_too_large(input: &str) {
+        let input = input
+            .replace("divisible by 23", "divisible by 4294967291")
+            .replace("divisible by 19", "divisible by 4294967279")
+            .replace("divisible by 13", "divisible by 4294967231");
+        let monkeys = load_all_monkeys(&input).unwrap();
+        let e = PART2.monkey_business(&monkeys, &mut Silent).unwrap_err();
+        assert_eq!(e.to_string(), "LCM of divisors exceeds u64");
+    }
+
+    #[rstest]
+    fn test_throw_to_missing_monkey(input: &str) {
+        let input = input.replace("throw to monkey 3", "throw to monkey 7");
+        let monkeys = load_all_monkeys(&input).unwrap();
+        let e = PART1.monkey_business(&monkeys, &mut Silent).unwrap_err();
+        assert_eq!(e.to_string(), "there is no monkey 7, only 4");
+    }
+
+    #[test]
+    fn test_relief_from_str() {
+        assert_eq!("none".parse::<Relief>().unwrap(), Relief::None);
+        assert_eq!("3".parse::<Relief>().unwrap(), Relief::DivideBy(3));
+        assert!("0".parse::<Relief>().is_err());
+        assert!("half".parse::<Relief>().is_err());
+    }
+}
@@ -0,0 +1,242 @@
+// What happens to each item, step by step, for anyone who wants to watch.
+// The simulation reports every step to a Trace, which ignores them unless
+// it says otherwise. Narration writes them out in the puzzle's own words:
+//
+//   Monkey 0:
+//     Monkey inspects an item with a worry level of 79.
+//       Worry level is multiplied by 19 to 1501.
+//       Monkey gets bored with item. Worry level is divided by 3 to 500.
+//       Current worry level is not divisible by 23.
+//       Item with worry level 500 is thrown to monkey 3.
+
+use crate::expr::{BinOp, Expr};
+use crate::parse::Monkey;
+use crate::simulation::RoundObserver;
+use std::fmt;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event<'a, V> {
+    Turn { monkey: usize },
+    Inspect { worry: &'a V },
+    Operation { operation: &'a Expr, worry: &'a V }, // worry afterwards
+    Relief { by: u64, worry: &'a V },
+    Test { divisor: u64, divisible: bool },
+    Throw { worry: &'a V, to: usize },
+}
+
+pub trait Trace {
+    fn record<V: fmt::Display>(&mut self, _event: Event<'_, V>) {}
+}
+
+// How an operation changed the worry level, in the puzzle's words where it
+// has them
+fn describe<V: fmt::Display>(operation: &Expr, worry: &V) -> String {
+    match operation {
+        Expr::Binary(BinOp::Add, old, y) if **old == Expr::Old => {
+            format!("Worry level increases by {y} to {worry}.")
+        }
+        Expr::Binary(BinOp::Mul, old, y) if **old == Expr::Old && **y == Expr::Old => {
+            format!("Worry level is multiplied by itself to {worry}.")
+        }
+        Expr::Binary(BinOp::Mul, old, y) if **old == Expr::Old => {
+            format!("Worry level is multiplied by {y} to {worry}.")
+        }
+        _ => format!("Worry level is changed by new = {operation} to {worry}."),
+    }
+}
+
+// Everything the monkeys do, and the items they hold after each round
+pub struct Narration<W> {
+    out: W,
+}
+
+impl<W: Write> Narration<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    #[cfg(test)]
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn line(&mut self, indent: usize, text: &str) {
+        writeln!(self.out, "{:indent$}{text}", "").expect("can write the narration");
+    }
+}
+
+impl<W: Write> Trace for Narration<W> {
+    fn record<V: fmt::Display>(&mut self, event: Event<'_, V>) {
+        match event {
+            Event::Turn { monkey } => self.line(0, &format!("Monkey {monkey}:")),
+            Event::Inspect { worry } => self.line(
+                2,
+                &format!("Monkey inspects an item with a worry level of {worry}."),
+            ),
+            Event::Operation { operation, worry } => self.line(4, &describe(operation, worry)),
+            Event::Relief { by, worry } => self.line(
+                4,
+                &format!("Monkey gets bored with item. Worry level is divided by {by} to {worry}."),
+            ),
+            Event::Test { divisor, divisible } => {
+                let not = if divisible { "" } else { "not " };
+                self.line(
+                    4,
+                    &format!("Current worry level is {not}divisible by {divisor}."),
+                )
+            }
+            Event::Throw { worry, to } => self.line(
+                4,
+                &format!("Item with worry level {worry} is thrown to monkey {to}."),
+            ),
+        }
+    }
+}
+
+impl<W: Write> RoundObserver for Narration<W> {
+    fn after_round<V: fmt::Display>(&mut self, round: usize, monkeys: &[Monkey<V>], _: &[u64]) {
+        self.line(0, "");
+        self.line(
+            0,
+            &format!("After round {round}, the monkeys are holding items with these worry levels:"),
+        );
+        for (i, monkey) in monkeys.iter().enumerate() {
+            let items: Vec<String> = monkey.items.iter().map(|item| item.0.to_string()).collect();
+            let line = format!("Monkey {i}: {}", items.join(", "));
+            self.line(0, line.trim_end());
+        }
+        self.line(0, "");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::load_all_monkeys;
+    use crate::simulation::{Relief, Simulation};
+    use crate::tests::input;
+    use crate::worry::Checked;
+    use pretty_assertions::assert_eq;
+    use rstest::*;
+
+    fn narrate(input: &str, rounds: usize) -> String {
+        let monkeys = load_all_monkeys(input).unwrap();
+        let mut simulation = Simulation::new(&monkeys, Checked, Relief::DivideBy(3));
+        let mut narration = Narration::new(vec![]);
+        simulation.run(rounds, &mut narration).unwrap();
+        String::from_utf8(narration.into_inner()).unwrap()
+    }
+
+    // The first round of the example, word for word as in the puzzle
+    #[rstest]
+    fn test_first_round(input: &str) {
+        let expected = "\
+Monkey 0:
+  Monkey inspects an item with a worry level of 79.
+    Worry level is multiplied by 19 to 1501.
+    Monkey gets bored with item. Worry level is divided by 3 to 500.
+    Current worry level is not divisible by 23.
+    Item with worry level 500 is thrown to monkey 3.
+  Monkey inspects an item with a worry level of 98.
+    Worry level is multiplied by 19 to 1862.
+    Monkey gets bored with item. Worry level is divided by 3 to 620.
+    Current worry level is not divisible by 23.
+    Item with worry level 620 is thrown to monkey 3.
+Monkey 1:
+  Monkey inspects an item with a worry level of 54.
+    Worry level increases by 6 to 60.
+    Monkey gets bored with item. Worry level is divided by 3 to 20.
+    Current worry level is not divisible by 19.
+    Item with worry level 20 is thrown to monkey 0.
+  Monkey inspects an item with a worry level of 65.
+    Worry level increases by 6 to 71.
+    Monkey gets bored with item. Worry level is divided by 3 to 23.
+    Current worry level is not divisible by 19.
+    Item with worry level 23 is thrown to monkey 0.
+  Monkey inspects an item with a worry level of 75.
+    Worry level increases by 6 to 81.
+    Monkey gets bored with item. Worry level is divided by 3 to 27.
+    Current worry level is not divisible by 19.
+    Item with worry level 27 is thrown to monkey 0.
+  Monkey inspects an item with a worry level of 74.
+    Worry level increases by 6 to 80.
+    Monkey gets bored with item. Worry level is divided by 3 to 26.
+    Current worry level is not divisible by 19.
+    Item with worry level 26 is thrown to monkey 0.
+Monkey 2:
+  Monkey inspects an item with a worry level of 79.
+    Worry level is multiplied by itself to 6241.
+    Monkey gets bored with item. Worry level is divided by 3 to 2080.
+    Current worry level is divisible by 13.
+    Item with worry level 2080 is thrown to monkey 1.
+  Monkey inspects an item with a worry level of 60.
+    Worry level is multiplied by itself to 3600.
+    Monkey gets bored with item. Worry level is divided by 3 to 1200.
+    Current worry level is not divisible by 13.
+    Item with worry level 1200 is thrown to monkey 3.
+  Monkey inspects an item with a worry level of 97.
+    Worry level is multiplied by itself to 9409.
+    Monkey gets bored with item. Worry level is divided by 3 to 3136.
+    Current worry level is not divisible by 13.
+    Item with worry level 3136 is thrown to monkey 3.
+Monkey 3:
+  Monkey inspects an item with a worry level of 74.
+    Worry level increases by 3 to 77.
+    Monkey gets bored with item. Worry level is divided by 3 to 25.
+    Current worry level is not divisible by 17.
+    Item with worry level 25 is thrown to monkey 1.
+  Monkey inspects an item with a worry level of 500.
+    Worry level increases by 3 to 503.
+    Monkey gets bored with item. Worry level is divided by 3 to 167.
+    Current worry level is not divisible by 17.
+    Item with worry level 167 is thrown to monkey 1.
+  Monkey inspects an item with a worry level of 620.
+    Worry level increases by 3 to 623.
+    Monkey gets bored with item. Worry level is divided by 3 to 207.
+    Current worry level is not divisible by 17.
+    Item with worry level 207 is thrown to monkey 1.
+  Monkey inspects an item with a worry level of 1200.
+    Worry level increases by 3 to 1203.
+    Monkey gets bored with item. Worry level is divided by 3 to 401.
+    Current worry level is not divisible by 17.
+    Item with worry level 401 is thrown to monkey 1.
+  Monkey inspects an item with a worry level of 3136.
+    Worry level increases by 3 to 3139.
+    Monkey gets bored with item. Worry level is divided by 3 to 1046.
+    Current worry level is not divisible by 17.
+    Item with worry level 1046 is thrown to monkey 1.
+
+After round 1, the monkeys are holding items with these worry levels:
+Monkey 0: 20, 23, 27, 26
+Monkey 1: 2080, 25, 167, 207, 401, 1046
+Monkey 2:
+Monkey 3:
+
+";
+        assert_eq!(narrate(input, 1), expected);
+    }
+
+    #[rstest]
+    fn test_later_rounds(input: &str) {
+        let narration = narrate(input, 20);
+        let expected = "
+After round 20, the monkeys are holding items with these worry levels:
+Monkey 0: 10, 12, 14, 26, 34
+Monkey 1: 245, 93, 53, 199, 115
+Monkey 2:
+Monkey 3:
+
+";
+        assert!(narration.ends_with(expected), "{narration}");
+    }
+
+    #[test]
+    fn test_describe_other_operations() {
+        let operation: Expr = "(old + 1) * 2".parse().unwrap();
+        assert_eq!(
+            describe(&operation, &8),
+            "Worry level is changed by new = (old + 1) * 2 to 8."
+        );
+    }
+}
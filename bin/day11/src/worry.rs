@@ -0,0 +1,254 @@
+// Arithmetic for worry levels. The same monkeys can be run with any of:
+//
+//   Checked - plain u64, where overflow is an error rather than a wrap.
+//   Modular - u64 reduced modulo the LCM of the monkeys' divisors. Every
+//             divisibility test gives the same answer as it would for the
+//             full number, so part 2 never overflows. Division can't be
+//             done this way, so there is no relief.
+//   Big     - arbitrary precision, for checking the others. Numbers grow
+//             very quickly without relief, so only for a few rounds.
+
+use crate::expr::{BinOp, EvalError};
+use color_eyre::eyre::{eyre, Report, Result};
+use num_bigint::BigUint;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+
+pub trait Arithmetic {
+    type Value: Clone + fmt::Debug + fmt::Display + Eq + Hash;
+
+    fn value(&self, n: u64) -> Self::Value;
+    fn apply(&self, op: BinOp, a: &Self::Value, b: &Self::Value) -> Result<Self::Value, EvalError>;
+
+    // The remainder of dividing by one of the monkeys' divisors
+    fn remainder(&self, value: &Self::Value, divisor: u64) -> u64;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checked;
+
+impl Arithmetic for Checked {
+    type Value = u64;
+
+    fn value(&self, n: u64) -> u64 {
+        n
+    }
+
+    fn apply(&self, op: BinOp, a: &u64, b: &u64) -> Result<u64, EvalError> {
+        op.apply(*a, *b)
+    }
+
+    fn remainder(&self, value: &u64, divisor: u64) -> u64 {
+        value % divisor
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Modular {
+    pub modulus: u64,
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl Modular {
+    pub fn new(modulus: u64) -> Self {
+        assert!(modulus > 0, "modulus must be at least 1");
+        Self { modulus }
+    }
+
+    // Modulo the least common multiple of the divisors, if it fits
+    pub fn for_divisors(divisors: impl IntoIterator<Item = u64>) -> Result<Self> {
+        let lcm = divisors.into_iter().try_fold(1u64, |lcm, divisor| {
+            (lcm / gcd(lcm, divisor))
+                .checked_mul(divisor)
+                .ok_or_else(|| eyre!("LCM of divisors exceeds u64"))
+        })?;
+        Ok(Self::new(lcm))
+    }
+}
+
+impl Arithmetic for Modular {
+    type Value = u64;
+
+    fn value(&self, n: u64) -> u64 {
+        n % self.modulus
+    }
+
+    fn apply(&self, op: BinOp, a: &u64, b: &u64) -> Result<u64, EvalError> {
+        let (a, b, m) = (*a as u128, *b as u128, self.modulus as u128);
+        let result = match op {
+            BinOp::Add => (a + b) % m,
+            BinOp::Sub => (a + m - b) % m,
+            BinOp::Mul => a * b % m,
+            BinOp::Div | BinOp::Rem => {
+                return Err(EvalError::NotModular {
+                    op,
+                    modulus: self.modulus,
+                })
+            }
+        };
+        Ok(result as u64)
+    }
+
+    // Only meaningful for divisors of the modulus
+    fn remainder(&self, value: &u64, divisor: u64) -> u64 {
+        value % divisor
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Big;
+
+impl Arithmetic for Big {
+    type Value = BigUint;
+
+    fn value(&self, n: u64) -> BigUint {
+        BigUint::from(n)
+    }
+
+    fn apply(&self, op: BinOp, a: &BigUint, b: &BigUint) -> Result<BigUint, EvalError> {
+        let zero = BigUint::from(0u8);
+        match op {
+            BinOp::Add => Ok(a + b),
+            BinOp::Sub if a < b => Err(EvalError::Overflow {
+                op,
+                a: a.to_string(),
+                b: b.to_string(),
+            }),
+            BinOp::Sub => Ok(a - b),
+            BinOp::Mul => Ok(a * b),
+            BinOp::Div | BinOp::Rem if *b == zero => Err(EvalError::DivisionByZero {
+                op,
+                a: a.to_string(),
+            }),
+            BinOp::Div => Ok(a / b),
+            BinOp::Rem => Ok(a % b),
+        }
+    }
+
+    fn remainder(&self, value: &BigUint, divisor: u64) -> u64 {
+        let remainder = value % divisor;
+        remainder.try_into().expect("less than the divisor")
+    }
+}
+
+// Which arithmetic to use, chosen on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Checked,
+    Modular,
+    Big,
+}
+
+// "checked", "modular" or "big"
+impl FromStr for Backend {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "checked" => Ok(Backend::Checked),
+            "modular" => Ok(Backend::Modular),
+            "big" => Ok(Backend::Big),
+            _ => Err(eyre!("Invalid arithmetic {s}")),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Backend::Checked => "checked",
+            Backend::Modular => "modular",
+            Backend::Big => "big",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Expr;
+
+    fn eval<A: Arithmetic>(arithmetic: &A, expr: &str, old: u64) -> Result<A::Value, EvalError> {
+        let expr: Expr = expr.parse().unwrap();
+        expr.eval_with(arithmetic, &arithmetic.value(old))
+    }
+
+    #[test]
+    fn test_for_divisors() {
+        let modulus = |divisors: &[u64]| Modular::for_divisors(divisors.to_vec()).unwrap().modulus;
+        assert_eq!(modulus(&[23, 19, 13, 17]), 96577);
+        // the lcm, not the product
+        assert_eq!(modulus(&[4, 6, 10]), 60);
+        assert_eq!(modulus(&[]), 1);
+        assert_eq!(modulus(&[1 << 32, 1 << 31, 1 << 32]), 1 << 32);
+
+        // primes near 2^32
+        let e = Modular::for_divisors([4294967291, 4294967279, 4294967231]).unwrap_err();
+        assert_eq!(e.to_string(), "LCM of divisors exceeds u64");
+    }
+
+    #[test]
+    fn test_modular() {
+        let modular = Modular::new(60);
+        assert_eq!(eval(&modular, "old * old + 5", 59), Ok(6));
+        assert_eq!(eval(&modular, "old - 10", 3), Ok(53));
+        assert_eq!(
+            eval(&modular, "old / 3", 9).unwrap_err().to_string(),
+            "/ can't be done modulo 60"
+        );
+
+        // no overflow, however large the modulus
+        let modular = Modular::new(u64::MAX);
+        assert_eq!(eval(&modular, "old * old", u64::MAX - 1), Ok(1));
+    }
+
+    #[test]
+    fn test_checked() {
+        assert_eq!(eval(&Checked, "old * old / 3", 9), Ok(27));
+        assert!(eval(&Checked, "old * old", 1 << 32).is_err());
+    }
+
+    #[test]
+    fn test_big() {
+        let value = eval(&Big, "old * old * old", 1 << 32).unwrap();
+        assert_eq!(value, BigUint::from(1u8) << 96);
+        assert_eq!(Big.remainder(&value, 7), (1u128 << 96).rem_euclid(7) as u64);
+        assert_eq!(
+            eval(&Big, "old - 10", 3).unwrap_err().to_string(),
+            "3 - 10 is out of range"
+        );
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        // the divisibility tests come out the same, whichever is used
+        let modular = Modular::for_divisors([2, 3, 5, 7]).unwrap();
+        for old in 0..50 {
+            let big = eval(&Big, "(old + 3) * old * old + 11", old).unwrap();
+            let small = eval(&modular, "(old + 3) * old * old + 11", old).unwrap();
+            for divisor in [2, 3, 5, 7] {
+                assert_eq!(
+                    Big.remainder(&big, divisor),
+                    modular.remainder(&small, divisor)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_backend_from_str() {
+        for backend in [Backend::Checked, Backend::Modular, Backend::Big] {
+            assert_eq!(backend.to_string().parse::<Backend>().unwrap(), backend);
+        }
+        assert!("float".parse::<Backend>().is_err());
+    }
+}
@@ -0,0 +1,192 @@
+// A row of stacks, addressed by position, that items are moved between in
+// batches, like crates between the piles on a dock.
+
+use crate::stack::Stack;
+use std::ops::{Index, IndexMut};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ArenaError {
+    #[error("there is no stack {stack}, only {stacks} stacks")]
+    NoSuchStack { stack: usize, stacks: usize },
+
+    #[error("stack {stack} has {available} items, not {wanted}")]
+    NotEnoughItems {
+        stack: usize,
+        wanted: usize,
+        available: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackArena<T> {
+    stacks: Vec<Stack<T>>,
+}
+
+impl<T> StackArena<T> {
+    // `count` empty stacks
+    pub fn new(count: usize) -> Self {
+        Self {
+            stacks: (0..count).map(|_| Stack::new()).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.stacks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+
+    pub fn get(&self, stack: usize) -> Option<&Stack<T>> {
+        self.stacks.get(stack)
+    }
+
+    pub fn get_mut(&mut self, stack: usize) -> Option<&mut Stack<T>> {
+        self.stacks.get_mut(stack)
+    }
+
+    pub fn heights(&self) -> Vec<usize> {
+        self.stacks.iter().map(Stack::len).collect()
+    }
+
+    pub fn tops(&self) -> impl Iterator<Item = Option<&T>> + '_ {
+        self.stacks.iter().map(Stack::peek)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Stack<T>> {
+        self.stacks.iter()
+    }
+
+    fn check(&self, stack: usize) -> Result<(), ArenaError> {
+        if stack < self.stacks.len() {
+            Ok(())
+        } else {
+            Err(ArenaError::NoSuchStack {
+                stack,
+                stacks: self.stacks.len(),
+            })
+        }
+    }
+
+    // Move the top `n` items of `src` onto `dst`. With `preserve_order` they
+    // keep their order, as if lifted together; without it they are moved one
+    // at a time, so they end up upside down. Moving items onto the stack they
+    // came from leaves it as it was, either way. Nothing moves if it fails.
+    pub fn move_n(
+        &mut self,
+        src: usize,
+        dst: usize,
+        n: usize,
+        preserve_order: bool,
+    ) -> Result<(), ArenaError> {
+        self.check(src)?;
+        self.check(dst)?;
+        let source = &mut self.stacks[src];
+        let available = source.len();
+        if n > available {
+            return Err(ArenaError::NotEnoughItems {
+                stack: src,
+                wanted: n,
+                available,
+            });
+        }
+        if src == dst {
+            return Ok(());
+        }
+        let mut items = source.pop_n(n).expect("enough items");
+        if !preserve_order {
+            items.reverse();
+        }
+        self.stacks[dst].extend(items);
+        Ok(())
+    }
+}
+
+impl<T> Index<usize> for StackArena<T> {
+    type Output = Stack<T>;
+
+    fn index(&self, stack: usize) -> &Stack<T> {
+        &self.stacks[stack]
+    }
+}
+
+impl<T> IndexMut<usize> for StackArena<T> {
+    fn index_mut(&mut self, stack: usize) -> &mut Stack<T> {
+        &mut self.stacks[stack]
+    }
+}
+
+impl<T> FromIterator<Stack<T>> for StackArena<T> {
+    fn from_iter<I: IntoIterator<Item = Stack<T>>>(iter: I) -> Self {
+        Self {
+            stacks: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arena() -> StackArena<char> {
+        ["ZN", "MCD", "P"]
+            .iter()
+            .map(|stack| stack.chars().collect())
+            .collect()
+    }
+
+    fn contents(arena: &StackArena<char>) -> Vec<String> {
+        arena.iter().map(|stack| stack.iter().collect()).collect()
+    }
+
+    #[test]
+    fn test_move_n() {
+        let mut arena = arena();
+        arena.move_n(1, 0, 2, false).unwrap();
+        assert_eq!(contents(&arena), ["ZNDC", "M", "P"]);
+        arena.move_n(0, 2, 3, true).unwrap();
+        assert_eq!(contents(&arena), ["Z", "M", "PNDC"]);
+        assert_eq!(arena.heights(), vec![1, 1, 4]);
+        assert_eq!(
+            arena.tops().map(|top| top.copied()).collect::<Vec<_>>(),
+            vec![Some('Z'), Some('M'), Some('C')]
+        );
+    }
+
+    #[test]
+    fn test_move_onto_itself() {
+        let mut arena = arena();
+        arena.move_n(1, 1, 3, false).unwrap();
+        assert_eq!(contents(&arena)[1], "MCD");
+        arena.move_n(1, 1, 2, true).unwrap();
+        assert_eq!(contents(&arena)[1], "MCD");
+        assert!(arena.move_n(1, 1, 4, false).is_err());
+    }
+
+    #[test]
+    fn test_move_n_errors() {
+        let mut arena = arena();
+        assert_eq!(
+            arena.move_n(2, 0, 2, true),
+            Err(ArenaError::NotEnoughItems {
+                stack: 2,
+                wanted: 2,
+                available: 1
+            })
+        );
+        assert_eq!(
+            arena.move_n(0, 3, 1, true),
+            Err(ArenaError::NoSuchStack {
+                stack: 3,
+                stacks: 3
+            })
+        );
+        assert_eq!(
+            arena.move_n(0, 3, 1, true).unwrap_err().to_string(),
+            "there is no stack 3, only 3 stacks"
+        );
+        // nothing moved
+        assert_eq!(arena, self::arena());
+    }
+}
@@ -0,0 +1,150 @@
+// A double-ended queue that never grows past a fixed capacity. Pushing onto a
+// full deque fails and hands the item back, rather than making room; see
+// RingBuffer for a queue that drops its oldest item instead.
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deque<T> {
+    capacity: usize,
+    items: VecDeque<T>, // front first
+}
+
+impl<T> Deque<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            items: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.items.len() == self.capacity
+    }
+
+    pub fn push_back(&mut self, item: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(item);
+        }
+        self.items.push_back(item);
+        Ok(())
+    }
+
+    pub fn push_front(&mut self, item: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(item);
+        }
+        self.items.push_front(item);
+        Ok(())
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.items.pop_front()
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.items.pop_back()
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.items.front()
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.items.back()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.items.get(index)
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    // Push every item onto the back, or none of them if they would not all
+    // fit. On failure the items are handed back.
+    pub fn try_extend<I>(&mut self, items: I) -> Result<(), Vec<T>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let items: Vec<T> = items.into_iter().collect();
+        if self.len() + items.len() > self.capacity {
+            return Err(items);
+        }
+        self.items.extend(items);
+        Ok(())
+    }
+
+    // Front to back
+    pub fn iter(&self) -> std::collections::vec_deque::Iter<'_, T> {
+        self.items.iter()
+    }
+}
+
+impl<T> IntoIterator for Deque<T> {
+    type Item = T;
+    type IntoIter = std::collections::vec_deque::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Deque<T> {
+    type Item = &'a T;
+    type IntoIter = std::collections::vec_deque::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounded() {
+        let mut deque = Deque::new(2);
+        assert_eq!(deque.push_back(1), Ok(()));
+        assert_eq!(deque.push_front(0), Ok(()));
+        assert!(deque.is_full());
+        assert_eq!(deque.push_back(2), Err(2));
+        assert_eq!(deque.push_front(-1), Err(-1));
+        assert_eq!(deque.iter().copied().collect::<Vec<_>>(), vec![0, 1]);
+
+        assert_eq!(deque.pop_back(), Some(1));
+        assert_eq!(deque.push_back(3), Ok(()));
+        assert_eq!((deque.front(), deque.back()), (Some(&0), Some(&3)));
+    }
+
+    #[test]
+    fn test_try_extend() {
+        let mut deque = Deque::new(3);
+        assert_eq!(deque.try_extend(['a', 'b']), Ok(()));
+        assert_eq!(deque.try_extend(['c', 'd']), Err(vec!['c', 'd']));
+        assert_eq!(deque.len(), 2);
+        assert_eq!(deque.try_extend(['c']), Ok(()));
+        assert_eq!(deque.into_iter().collect::<String>(), "abc");
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut deque = Deque::new(0);
+        assert!(deque.is_full() && deque.is_empty());
+        assert_eq!(deque.push_back(()), Err(()));
+    }
+}
@@ -1,4 +1,10 @@
+pub mod arena;
+pub mod deque;
+pub mod parse;
+pub mod parsers;
+pub mod ring;
 pub mod stack;
+pub mod window;
 
 use color_eyre::eyre::{eyre, Context, Result};
 use std::env;
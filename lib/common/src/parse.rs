@@ -0,0 +1,230 @@
+// Running nom parsers over a whole input, and reporting where it went wrong.
+//
+// Parsers take a Span, which tracks line and column as it goes, and use
+// nom_supreme's ErrorTree so that failures keep their context. parse_all()
+// runs a parser to the end of the input and turns any failure into a miette
+// report that points at the offending input, e.g.
+//
+//   × bad input at line 2, column 3
+//    ╭─[1:1]
+//  1 │ 2-4,6-8
+//  2 │ 2-x,4-5
+//    ·   ▲
+//    ·   ╰── expected an ascii digit
+//    ╰────
+
+use color_eyre::eyre::{eyre, Result};
+use miette::{Diagnostic, GraphicalReportHandler, GraphicalTheme, LabeledSpan, SourceCode};
+use nom::IResult;
+use nom_locate::LocatedSpan;
+use nom_supreme::error::{BaseErrorKind, ErrorTree, GenericErrorTree};
+use nom_supreme::final_parser::final_parser;
+
+pub type Span<'a> = LocatedSpan<&'a str>;
+
+// For parsers that don't need to be generic over the error type
+pub type ParseResult<'a, T> = IResult<Span<'a>, T, ErrorTree<Span<'a>>>;
+
+#[derive(thiserror::Error, Debug)]
+#[error("bad input at line {line}, column {column}")]
+pub struct BadInput {
+    src: String,
+    labels: Vec<(usize, String)>, // offset, description
+    offset: usize,
+    pub line: u32,
+    pub column: usize,
+}
+
+impl BadInput {
+    pub fn new(input: &str, e: &ErrorTree<Span>) -> Self {
+        let mut found = vec![];
+        collect_labels(e, &mut found);
+
+        // one label per position, listing each different problem there once
+        let mut labels: Vec<(usize, Vec<String>)> = vec![];
+        for (span, label) in found {
+            let offset = span.location_offset();
+            match labels.iter_mut().find(|(o, _)| *o == offset) {
+                Some((_, here)) if here.contains(&label) => {}
+                Some((_, here)) => here.push(label),
+                None => labels.push((offset, vec![label])),
+            }
+        }
+
+        // point at whichever alternative got furthest
+        let location = furthest(e);
+        Self {
+            src: input.to_owned(),
+            labels: labels
+                .into_iter()
+                .map(|(offset, here)| (offset, here.join(" or ")))
+                .collect(),
+            offset: location.location_offset(),
+            line: location.location_line(),
+            column: location.get_utf8_column(),
+        }
+    }
+
+    // What went wrong at the reported position, without the surrounding input
+    pub fn reason(&self) -> &str {
+        self.labels
+            .iter()
+            .find(|(offset, _)| *offset == self.offset)
+            .map_or("", |(_, label)| label)
+    }
+
+    pub fn render(&self) -> String {
+        let mut s = String::new();
+        GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+            .render_report(&mut s, self)
+            .expect("writing to a String");
+        s
+    }
+}
+
+impl Diagnostic for BadInput {
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.src)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        Some(Box::new(self.labels.iter().map(|(offset, label)| {
+            LabeledSpan::new(Some(label.clone()), *offset, 0)
+        })))
+    }
+}
+
+fn describe(
+    kind: &BaseErrorKind<&'static str, Box<dyn std::error::Error + Send + Sync>>,
+) -> String {
+    match kind {
+        // the default display adds an "external error:" header
+        BaseErrorKind::External(e) => e.to_string(),
+        kind => kind.to_string(),
+    }
+}
+
+// Innermost errors first, then the contexts they were found in
+fn collect_labels<'a>(e: &ErrorTree<Span<'a>>, labels: &mut Vec<(Span<'a>, String)>) {
+    match e {
+        GenericErrorTree::Base { location, kind } => labels.push((*location, describe(kind))),
+        GenericErrorTree::Stack { base, contexts } => {
+            collect_labels(base, labels);
+            for (location, context) in contexts {
+                labels.push((*location, context.to_string()));
+            }
+        }
+        GenericErrorTree::Alt(alternatives) => {
+            for alternative in alternatives {
+                collect_labels(alternative, labels);
+            }
+        }
+    }
+}
+
+fn furthest<'a>(e: &ErrorTree<Span<'a>>) -> Span<'a> {
+    match e {
+        GenericErrorTree::Base { location, .. } => *location,
+        GenericErrorTree::Stack { base, .. } => furthest(base),
+        GenericErrorTree::Alt(alternatives) => alternatives
+            .iter()
+            .map(furthest)
+            .max_by_key(|location| location.location_offset())
+            .expect("at least one alternative"),
+    }
+}
+
+// Run a parser over the whole input
+pub fn parse_complete<'a, T>(
+    parser: impl FnMut(Span<'a>) -> ParseResult<'a, T>,
+    input: &'a str,
+) -> Result<T, BadInput> {
+    final_parser(parser)(Span::new(input)).map_err(|e: ErrorTree<Span>| BadInput::new(input, &e))
+}
+
+// As parse_complete(), rendering any error as a report
+pub fn parse_all<'a, T>(
+    parser: impl FnMut(Span<'a>) -> ParseResult<'a, T>,
+    input: &'a str,
+) -> Result<T> {
+    parse_complete(parser, input).map_err(|e| eyre!(e.render()))
+}
+
+// Run a parser over the start of the input, returning the rest as a &str.
+// Handy for testing individual parsers.
+pub fn parse_prefix<'a, T>(
+    mut parser: impl FnMut(Span<'a>) -> ParseResult<'a, T>,
+    input: &'a str,
+) -> Result<(&'a str, T), nom::Err<ErrorTree<Span<'a>>>> {
+    let (rest, value) = parser(Span::new(input))?;
+    Ok((*rest.fragment(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::branch::alt;
+    use nom::character::complete::{char, newline, u32};
+    use nom::multi::separated_list1;
+    use nom::sequence::terminated;
+    use nom::Parser;
+    use nom_supreme::parser_ext::ParserExt;
+    use nom_supreme::tag::complete::tag;
+
+    fn numbers(i: Span) -> ParseResult<Vec<u32>> {
+        separated_list1(newline, u32)(i)
+    }
+
+    #[test]
+    fn test_parse_all() {
+        assert_eq!(parse_all(numbers, "1\n22\n333").unwrap(), vec![1, 22, 333]);
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        assert_eq!(parse_prefix(numbers, "1\n2x").unwrap(), ("x", vec![1, 2]));
+        assert!(parse_prefix(numbers, "x").is_err());
+    }
+
+    #[test]
+    fn test_base_error() {
+        let e = parse_all(numbers, "1\n22x\n333").unwrap_err().to_string();
+        assert!(e.contains("bad input at line 2, column 3"), "{e}");
+        assert!(e.contains("expected eof"), "{e}");
+    }
+
+    #[test]
+    fn test_stack_error() {
+        let mut pair = terminated(u32, char(';')).context("pair");
+        let e = parse_all(|i| pair.parse(i), "12,").unwrap_err().to_string();
+        assert!(e.contains("bad input at line 1, column 3"), "{e}");
+        assert!(e.contains("expected ';'"), "{e}");
+        assert!(e.contains("in section \"pair\""), "{e}");
+    }
+
+    #[test]
+    fn test_alt_error() {
+        let parser = alt((tag("cd "), terminated(tag("ls"), newline)));
+        let e = parse_all(parser, "lsx").unwrap_err().to_string();
+        // the furthest alternative gives the position
+        assert!(e.contains("bad input at line 1, column 3"), "{e}");
+        assert!(e.contains("expected \"cd \""), "{e}");
+        assert!(e.contains("expected '\\n'"), "{e}");
+
+        // problems at the same place share a label
+        let parser = alt((tag("cd "), tag("ls")));
+        let e = parse_all(parser, "x").unwrap_err().to_string();
+        assert!(e.contains("expected \"cd \" or expected \"ls\""), "{e}");
+    }
+
+    #[test]
+    fn test_bad_input() {
+        let e = final_parser(numbers)(Span::new("1\n\n2")).unwrap_err();
+        let bad = BadInput::new("1\n\n2", &e);
+        assert_eq!((bad.line, bad.column), (1, 2));
+        assert_eq!(bad.reason(), "expected eof");
+
+        let bad = parse_complete(numbers, "1\nx").unwrap_err();
+        assert_eq!((bad.line, bad.column), (1, 2));
+    }
+}
@@ -0,0 +1,339 @@
+// Parsers for the shapes of input that keep coming up: numbers one per line,
+// groups of lines separated by blank lines, grids of characters, "U 4" style
+// steps (diagonals too) and "move N from A to B" instructions.
+//
+// They work on located spans, so run them with parse::parse_all() (or
+// parse_lines() below) to get errors that point at the problem. Anything
+// after a line break is expected to be another line, so callers should trim
+// trailing newlines from the input first.
+
+use crate::parse::{parse_all, ParseResult, Span};
+use color_eyre::eyre::Result;
+use nom::bytes::complete::{take_till, take_while};
+use nom::character::complete::{char, digit1, newline, space1};
+use nom::combinator::{cut, map, map_res, not, opt, recognize};
+use nom::error::{ErrorKind, FromExternalError};
+use nom::multi::separated_list1;
+use nom::sequence::{pair, preceded, terminated, tuple};
+use nom::Slice;
+use nom_supreme::error::ErrorTree;
+use nom_supreme::tag::complete::tag;
+use std::str::FromStr;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ShapeError {
+    #[error("unexpected character {0:?}")]
+    UnexpectedChar(char),
+
+    #[error("row has {found} cells, expected {expected}")]
+    RaggedRow { found: usize, expected: usize },
+
+    #[error("empty row")]
+    EmptyRow,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum StepError {
+    #[error("unknown direction {0:?}")]
+    UnknownDirection(String),
+
+    #[error("missing count")]
+    MissingCount,
+
+    #[error("negative count {0}")]
+    NegativeCount(isize),
+}
+
+fn failure<'a, E>(location: Span<'a>, e: E) -> nom::Err<ErrorTree<Span<'a>>>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    nom::Err::Failure(ErrorTree::from_external_error(
+        location,
+        ErrorKind::Verify,
+        e,
+    ))
+}
+
+// A decimal number, optionally negative. Whether a sign is allowed, and how
+// large the number may be, is up to T.
+pub fn number<'a, T>(i: Span<'a>) -> ParseResult<'a, T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    map_res(recognize(pair(opt(char('-')), digit1)), |s: Span| {
+        s.fragment().parse::<T>()
+    })(i)
+}
+
+// One item per line
+pub fn lines<'a, T>(
+    item: impl FnMut(Span<'a>) -> ParseResult<'a, T>,
+) -> impl FnMut(Span<'a>) -> ParseResult<'a, Vec<T>> {
+    separated_list1(newline, cut(item))
+}
+
+// Groups of lines, separated by blank lines
+pub fn groups<'a, T>(
+    item: impl FnMut(Span<'a>) -> ParseResult<'a, T>,
+) -> impl FnMut(Span<'a>) -> ParseResult<'a, Vec<Vec<T>>> {
+    // a line break followed by another one ends the group
+    let group = separated_list1(terminated(newline, not(newline)), cut(item));
+    separated_list1(pair(newline, newline), cut(group))
+}
+
+// A rectangle of characters, turned into cells by `cell`, row by row
+pub fn grid<'a, T>(
+    cell: impl Fn(char) -> Option<T>,
+) -> impl FnMut(Span<'a>) -> ParseResult<'a, Vec<Vec<T>>> {
+    move |i| {
+        let row = |i: Span<'a>| -> ParseResult<'a, (Span<'a>, Vec<T>)> {
+            let (rest, text) = take_till(|c| c == '\n')(i)?;
+            let cells = text
+                .fragment()
+                .char_indices()
+                .map(|(at, c)| {
+                    cell(c).ok_or_else(|| failure(text.slice(at..), ShapeError::UnexpectedChar(c)))
+                })
+                .collect::<Result<Vec<T>, _>>()?;
+            Ok((rest, (text, cells)))
+        };
+
+        let (rest, rows) = separated_list1(newline, row)(i)?;
+
+        let expected = rows[0].1.len();
+        for (start, cells) in &rows {
+            if cells.is_empty() {
+                return Err(failure(*start, ShapeError::EmptyRow));
+            }
+            if cells.len() != expected {
+                let found = cells.len();
+                return Err(failure(*start, ShapeError::RaggedRow { found, expected }));
+            }
+        }
+
+        Ok((rest, rows.into_iter().map(|(_, cells)| cells).collect()))
+    }
+}
+
+// A digit from 0 to 9, for use as a grid cell
+pub fn digit(c: char) -> Option<u32> {
+    c.to_digit(10)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    // One step in this direction, with y going up
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Direction::Up => (0, 1),
+            Direction::Down => (0, -1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::UpLeft => (-1, 1),
+            Direction::UpRight => (1, 1),
+            Direction::DownLeft => (-1, -1),
+            Direction::DownRight => (1, -1),
+        }
+    }
+}
+
+// "U", "D", "L", "R", or a diagonal such as "UL" or "DR"
+pub fn direction(i: Span) -> ParseResult<Direction> {
+    let (rest, word) = take_while(|c: char| c.is_ascii_alphabetic())(i)?;
+    let direction = match *word.fragment() {
+        "U" => Direction::Up,
+        "D" => Direction::Down,
+        "L" => Direction::Left,
+        "R" => Direction::Right,
+        "UL" => Direction::UpLeft,
+        "UR" => Direction::UpRight,
+        "DL" => Direction::DownLeft,
+        "DR" => Direction::DownRight,
+        other => return Err(failure(i, StepError::UnknownDirection(other.to_owned()))),
+    };
+    Ok((rest, direction))
+}
+
+// A direction and how many steps to take in it, e.g. "R 4"
+pub fn direction_count(i: Span) -> ParseResult<(Direction, usize)> {
+    let (i, direction) = direction(i)?;
+    let (i, _) =
+        space1::<_, ErrorTree<Span>>(i).map_err(|_| failure(i, StepError::MissingCount))?;
+    let (rest, count) = cut(number::<isize>)(i)?;
+    let count = usize::try_from(count).map_err(|_| failure(i, StepError::NegativeCount(count)))?;
+    Ok((rest, (direction, count)))
+}
+
+// "move N from A to B", with the numbers as written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CraneMove {
+    pub count: usize,
+    pub from: usize,
+    pub to: usize,
+}
+
+pub fn crane_move(i: Span) -> ParseResult<CraneMove> {
+    map(
+        tuple((
+            preceded(tag("move "), cut(number)),
+            preceded(cut(tag(" from ")), cut(number)),
+            preceded(cut(tag(" to ")), cut(number)),
+        )),
+        |(count, from, to)| CraneMove { count, from, to },
+    )(i)
+}
+
+// Parse each line of the input with `item`, reporting errors by line number
+pub fn parse_lines<T>(
+    item: impl for<'a> FnMut(Span<'a>) -> ParseResult<'a, T>,
+    input: &[String],
+) -> Result<Vec<T>> {
+    let text = input.join("\n");
+    parse_all(lines(item), text.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_complete, parse_prefix};
+    use crate::vec_of_strings;
+
+    fn error<'a, T: std::fmt::Debug>(
+        parser: impl FnMut(Span<'a>) -> ParseResult<'a, T>,
+        input: &'a str,
+    ) -> (u32, usize, String) {
+        let e = parse_complete(parser, input).unwrap_err();
+        (e.line, e.column, e.reason().to_owned())
+    }
+
+    #[test]
+    fn test_number() {
+        assert_eq!(parse_prefix(number::<u32>, "123 ").unwrap(), (" ", 123));
+        assert_eq!(parse_prefix(number::<i64>, "-42").unwrap(), ("", -42));
+        assert_eq!(
+            error(number::<u32>, "-42"),
+            (1, 1, "invalid digit found in string".to_owned())
+        );
+        assert_eq!(
+            error(number::<u8>, "256"),
+            (1, 1, "number too large to fit in target type".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        let input = vec_of_strings!["1", "2", "3"];
+        assert_eq!(parse_lines(number::<u32>, &input).unwrap(), vec![1, 2, 3]);
+
+        assert_eq!(
+            error(lines(number::<u32>), "1\n2\nx"),
+            (3, 1, "expected an ascii digit".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_groups() {
+        assert_eq!(
+            parse_all(groups(number::<u32>), "1\n2\n\n3\n\n4\n5\n6").unwrap(),
+            vec![vec![1, 2], vec![3], vec![4, 5, 6]]
+        );
+        assert_eq!(
+            parse_all(groups(number::<u32>), "7").unwrap(),
+            vec![vec![7]]
+        );
+
+        // two blank lines in a row
+        assert_eq!(
+            error(groups(number::<u32>), "1\n\n\n2"),
+            (3, 1, "expected an ascii digit".to_owned())
+        );
+        assert_eq!(
+            error(groups(number::<u32>), "1\n2\nx\n\n3"),
+            (3, 1, "expected an ascii digit".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_grid() {
+        assert_eq!(
+            parse_all(grid(digit), "123\n456").unwrap(),
+            vec![vec![1, 2, 3], vec![4, 5, 6]]
+        );
+        assert_eq!(
+            parse_all(grid(|c| Some(c == '#')), ".#\n#.").unwrap(),
+            vec![vec![false, true], vec![true, false]]
+        );
+
+        assert_eq!(
+            error(grid(digit), "123\n4x6"),
+            (2, 2, "unexpected character 'x'".to_owned())
+        );
+        assert_eq!(
+            error(grid(digit), "123\n45\n789"),
+            (2, 1, "row has 2 cells, expected 3".to_owned())
+        );
+        assert_eq!(
+            error(grid(digit), "123\n\n789"),
+            (2, 1, "empty row".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_direction_count() {
+        assert_eq!(
+            parse_prefix(direction_count, "R 4").unwrap(),
+            ("", (Direction::Right, 4))
+        );
+        assert_eq!(
+            parse_prefix(direction_count, "UL 13\n").unwrap(),
+            ("\n", (Direction::UpLeft, 13))
+        );
+        assert_eq!(Direction::DownRight.offset(), (1, -1));
+
+        assert_eq!(
+            error(lines(direction_count), "R 4\nU -1"),
+            (2, 3, "negative count -1".to_owned())
+        );
+        assert_eq!(
+            error(lines(direction_count), "R 4\nU"),
+            (2, 2, "missing count".to_owned())
+        );
+        assert_eq!(
+            error(lines(direction_count), "LU 4"),
+            (1, 1, "unknown direction \"LU\"".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_crane_move() {
+        assert_eq!(
+            parse_all(crane_move, "move 1 from 2 to 3").unwrap(),
+            CraneMove {
+                count: 1,
+                from: 2,
+                to: 3
+            }
+        );
+        assert_eq!(
+            error(crane_move, "move 1 from 2"),
+            (1, 14, "expected \" to \"".to_owned())
+        );
+        assert_eq!(
+            error(crane_move, "move x from 2 to 3"),
+            (1, 6, "expected an ascii digit".to_owned())
+        );
+    }
+}
@@ -0,0 +1,116 @@
+// A fixed-size ring buffer. Once it is full, each push overwrites the oldest
+// item, so it always holds the most recent `capacity` items. Indexing and
+// iteration go from the oldest item to the newest.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RingBuffer<T> {
+    capacity: usize,
+    slots: Vec<T>, // grows to capacity, then wraps
+    head: usize,   // the oldest item, once full
+}
+
+impl<T> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "ring buffer capacity must be at least 1");
+        Self {
+            capacity,
+            slots: Vec::with_capacity(capacity),
+            head: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.slots.len() == self.capacity
+    }
+
+    // Add an item, returning the one it overwrote, if the buffer was full
+    pub fn push(&mut self, item: T) -> Option<T> {
+        if !self.is_full() {
+            self.slots.push(item);
+            return None;
+        }
+        let old = std::mem::replace(&mut self.slots[self.head], item);
+        self.head = (self.head + 1) % self.capacity;
+        Some(old)
+    }
+
+    // Counting from the oldest item
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len() {
+            return None;
+        }
+        self.slots.get((self.head + index) % self.capacity)
+    }
+
+    pub fn oldest(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn newest(&self) -> Option<&T> {
+        self.len().checked_sub(1).and_then(|last| self.get(last))
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.head = 0;
+    }
+
+    // Oldest to newest
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        let (newer, older) = self.slots.split_at(self.head);
+        older.iter().chain(newer)
+    }
+}
+
+impl<T> Extend<T> for RingBuffer<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overwrite() {
+        let mut ring = RingBuffer::new(3);
+        assert_eq!(ring.push(1), None);
+        assert_eq!(ring.push(2), None);
+        assert_eq!(ring.push(3), None);
+        assert_eq!(ring.push(4), Some(1));
+        assert_eq!(ring.push(5), Some(2));
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!((ring.oldest(), ring.newest()), (Some(&3), Some(&5)));
+        assert_eq!(ring.get(1), Some(&4));
+        assert_eq!(ring.get(3), None);
+    }
+
+    #[test]
+    fn test_extend_and_clear() {
+        let mut ring = RingBuffer::new(4);
+        ring.extend(0..10);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), vec![6, 7, 8, 9]);
+        assert_eq!(ring.len(), 4);
+
+        ring.clear();
+        assert!(ring.is_empty());
+        assert_eq!(ring.newest(), None);
+        ring.extend([1, 2]);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+    }
+}
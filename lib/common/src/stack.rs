@@ -1,5 +1,10 @@
+// A last-in, first-out stack. Iteration goes from the bottom of the stack to
+// the top, the order the items were pushed in, so a stack can be collected
+// from an iterator and read back the same way.
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Stack<T> {
-    elements: Vec<T>,
+    elements: Vec<T>, // bottom first
 }
 
 impl<T> Default for Stack<T> {
@@ -15,6 +20,25 @@ impl<T> Stack<T> {
         }
     }
 
+    pub fn with_capacity(capacity: usize) -> Self {
+        Stack {
+            elements: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.elements.capacity()
+    }
+
+    // Make room for at least `additional` more items
+    pub fn reserve(&mut self, additional: usize) {
+        self.elements.reserve(additional);
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.elements.shrink_to_fit();
+    }
+
     pub fn push(&mut self, item: T) {
         self.elements.push(item);
     }
@@ -27,11 +51,140 @@ impl<T> Stack<T> {
         self.elements.last()
     }
 
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        self.elements.last_mut()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.elements.is_empty()
     }
 
-    pub fn size(&self) -> usize {
+    pub fn len(&self) -> usize {
         self.elements.len()
     }
+
+    pub fn size(&self) -> usize {
+        self.len()
+    }
+
+    // The item `depth` places up from the bottom
+    pub fn get(&self, depth: usize) -> Option<&T> {
+        self.elements.get(depth)
+    }
+
+    pub fn clear(&mut self) {
+        self.elements.clear();
+    }
+
+    // Remove the top `n` items, bottom-most first, or None if there are fewer
+    // than `n` (the stack is left alone)
+    pub fn pop_n(&mut self, n: usize) -> Option<Vec<T>> {
+        let new_len = self.elements.len().checked_sub(n)?;
+        Some(self.elements.split_off(new_len))
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.elements
+    }
+
+    // Bottom to top
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.elements.iter()
+    }
+}
+
+impl<T> From<Vec<T>> for Stack<T> {
+    // The last item of the vector ends up on top
+    fn from(elements: Vec<T>) -> Self {
+        Stack { elements }
+    }
+}
+
+impl<T> From<Stack<T>> for Vec<T> {
+    fn from(stack: Stack<T>) -> Self {
+        stack.elements
+    }
+}
+
+impl<T> FromIterator<T> for Stack<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Stack {
+            elements: Vec::from_iter(iter),
+        }
+    }
+}
+
+impl<T> Extend<T> for Stack<T> {
+    // Pushes each item in turn
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.elements.extend(iter);
+    }
+}
+
+impl<T> IntoIterator for Stack<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Stack<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_pop() {
+        let mut stack = Stack::new();
+        assert!(stack.is_empty());
+        stack.push(1);
+        stack.push(2);
+        assert_eq!(stack.peek(), Some(&2));
+        *stack.peek_mut().unwrap() = 3;
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn test_iteration_order() {
+        let mut stack: Stack<char> = "abc".chars().collect();
+        stack.extend(['d', 'e']);
+        assert_eq!(stack.peek(), Some(&'e'));
+        assert_eq!(stack.get(0), Some(&'a'));
+        assert_eq!(stack.iter().collect::<String>(), "abcde");
+        assert_eq!(stack.clone().into_iter().collect::<String>(), "abcde");
+        assert_eq!(stack.len(), 5);
+    }
+
+    #[test]
+    fn test_pop_n() {
+        let mut stack = Stack::from(vec![1, 2, 3, 4]);
+        assert_eq!(stack.pop_n(5), None);
+        assert_eq!(stack.len(), 4);
+        assert_eq!(stack.pop_n(3), Some(vec![2, 3, 4]));
+        assert_eq!(stack.as_slice(), [1]);
+        assert_eq!(stack.pop_n(0), Some(vec![]));
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut stack: Stack<u8> = Stack::with_capacity(10);
+        assert!(stack.capacity() >= 10);
+        stack.reserve(100);
+        assert!(stack.capacity() >= 100);
+        stack.push(1);
+        stack.shrink_to_fit();
+        assert!(stack.capacity() >= 1);
+    }
 }
@@ -0,0 +1,378 @@
+// Sliding windows that keep a count of each item they hold, so that questions
+// like "are all the items different?" can be answered as the window moves,
+// without rescanning it.
+//
+// The counting is done by a Counter. HashCounter works for any hashable type,
+// ArrayCounter is faster for types with a small alphabet (see Symbol). If all
+// that matters is whether every item is distinct, and the alphabet has no
+// more than 64 symbols, BitmaskWindow does it without any counting at all.
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+pub trait Counter<T> {
+    fn add(&mut self, item: &T);
+    fn remove(&mut self, item: &T);
+    fn count(&self, item: &T) -> usize;
+
+    // Number of items with a non-zero count
+    fn distinct(&self) -> usize;
+}
+
+#[derive(Debug, Clone)]
+pub struct HashCounter<T> {
+    counts: HashMap<T, usize>, // only non-zero counts
+}
+
+impl<T> Default for HashCounter<T> {
+    fn default() -> Self {
+        Self {
+            counts: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Hash + Eq + Clone> Counter<T> for HashCounter<T> {
+    fn add(&mut self, item: &T) {
+        *self.counts.entry(item.clone()).or_default() += 1;
+    }
+
+    fn remove(&mut self, item: &T) {
+        let count = self.counts.get_mut(item).expect("item was added");
+        *count -= 1;
+        if *count == 0 {
+            self.counts.remove(item);
+        }
+    }
+
+    fn count(&self, item: &T) -> usize {
+        self.counts.get(item).copied().unwrap_or(0)
+    }
+
+    fn distinct(&self) -> usize {
+        self.counts.len()
+    }
+}
+
+// A type with a small, fixed set of values, numbered 0..ALPHABET
+pub trait Symbol: Copy {
+    const ALPHABET: usize;
+    fn index(self) -> usize;
+}
+
+impl Symbol for u8 {
+    const ALPHABET: usize = 256;
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// An ASCII letter, 'a'..='z' then 'A'..='Z'. Small enough for BitmaskWindow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Letter(u8);
+
+impl TryFrom<u8> for Letter {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            b'a'..=b'z' => Ok(Letter(value - b'a')),
+            b'A'..=b'Z' => Ok(Letter(value - b'A' + 26)),
+            _ => Err(value),
+        }
+    }
+}
+
+impl Symbol for Letter {
+    const ALPHABET: usize = 52;
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArrayCounter<S> {
+    counts: Vec<usize>,
+    distinct: usize,
+    symbol: PhantomData<S>,
+}
+
+impl<S: Symbol> Default for ArrayCounter<S> {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; S::ALPHABET],
+            distinct: 0,
+            symbol: PhantomData,
+        }
+    }
+}
+
+impl<S: Symbol> Counter<S> for ArrayCounter<S> {
+    fn add(&mut self, item: &S) {
+        let count = &mut self.counts[item.index()];
+        if *count == 0 {
+            self.distinct += 1;
+        }
+        *count += 1;
+    }
+
+    fn remove(&mut self, item: &S) {
+        let count = &mut self.counts[item.index()];
+        *count = count.checked_sub(1).expect("item was added");
+        if *count == 0 {
+            self.distinct -= 1;
+        }
+    }
+
+    fn count(&self, item: &S) -> usize {
+        self.counts[item.index()]
+    }
+
+    fn distinct(&self) -> usize {
+        self.distinct
+    }
+}
+
+// A window over the last `length` items pushed
+#[derive(Debug, Clone)]
+pub struct CountingWindow<T, C = HashCounter<T>> {
+    length: usize,
+    items: VecDeque<T>,
+    counter: C,
+}
+
+impl<T, C: Counter<T> + Default> CountingWindow<T, C> {
+    pub fn new(length: usize) -> Self {
+        assert!(length > 0, "window length must be at least 1");
+        Self {
+            length,
+            items: VecDeque::with_capacity(length),
+            counter: C::default(),
+        }
+    }
+
+    // Add an item, returning the oldest one if it no longer fits
+    pub fn push(&mut self, item: T) -> Option<T> {
+        let removed = if self.is_full() {
+            let removed = self.items.pop_front().expect("window is full");
+            self.counter.remove(&removed);
+            Some(removed)
+        } else {
+            None
+        };
+
+        self.counter.add(&item);
+        self.items.push_back(item);
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.items.len() == self.length
+    }
+
+    pub fn count(&self, item: &T) -> usize {
+        self.counter.count(item)
+    }
+
+    pub fn distinct(&self) -> usize {
+        self.counter.distinct()
+    }
+
+    pub fn all_distinct(&self) -> bool {
+        self.distinct() == self.len()
+    }
+
+    pub fn has_k_distinct(&self, k: usize) -> bool {
+        self.distinct() == k
+    }
+
+    // The item that appears most often, with its count. Ties go to the item
+    // that has been in the window longest.
+    pub fn most_frequent(&self) -> Option<(&T, usize)> {
+        self.items
+            .iter()
+            .map(|item| (item, self.count(item)))
+            .fold(None, |best, (item, count)| match best {
+                Some((_, best_count)) if best_count >= count => best,
+                _ => Some((item, count)),
+            })
+    }
+
+    // Oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+}
+
+// Distinctness only, for alphabets of up to 64 symbols. Each symbol toggles
+// its bit as it enters and leaves the window, so a bit is set if the symbol
+// appears an odd number of times. Any repeat leaves fewer bits set than there
+// are items, so the items are all distinct exactly when the counts match.
+#[derive(Debug, Clone)]
+pub struct BitmaskWindow<S> {
+    length: usize,
+    items: VecDeque<S>,
+    mask: u64,
+}
+
+impl<S: Symbol> BitmaskWindow<S> {
+    pub fn new(length: usize) -> Self {
+        assert!(S::ALPHABET <= 64, "alphabet is too large for a bitmask");
+        assert!(length > 0, "window length must be at least 1");
+        Self {
+            length,
+            items: VecDeque::with_capacity(length),
+            mask: 0,
+        }
+    }
+
+    pub fn push(&mut self, item: S) -> Option<S> {
+        let removed = if self.is_full() {
+            let removed = self.items.pop_front().expect("window is full");
+            self.mask ^= 1 << removed.index();
+            Some(removed)
+        } else {
+            None
+        };
+
+        self.mask ^= 1 << item.index();
+        self.items.push_back(item);
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.items.len() == self.length
+    }
+
+    pub fn all_distinct(&self) -> bool {
+        self.mask.count_ones() as usize == self.items.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letters(s: &str) -> Vec<Letter> {
+        s.bytes().map(|b| Letter::try_from(b).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_counting_window() {
+        let mut w = CountingWindow::<char>::new(3);
+        assert!(w.is_empty());
+        assert!(w.all_distinct());
+
+        assert_eq!(w.push('a'), None);
+        assert_eq!(w.push('b'), None);
+        assert_eq!(w.push('a'), None);
+        assert!(w.is_full());
+        assert_eq!(w.count(&'a'), 2);
+        assert_eq!(w.distinct(), 2);
+        assert!(!w.all_distinct());
+        assert!(w.has_k_distinct(2));
+
+        assert_eq!(w.push('c'), Some('a'));
+        assert_eq!(w.count(&'a'), 1);
+        assert!(w.all_distinct());
+        assert_eq!(w.iter().collect::<String>(), "bac");
+    }
+
+    #[test]
+    fn test_most_frequent() {
+        let mut w = CountingWindow::<&str>::new(5);
+        assert_eq!(w.most_frequent(), None);
+
+        for s in ["x", "y", "y", "x", "z"] {
+            w.push(s);
+        }
+        assert_eq!(w.most_frequent(), Some((&"x", 2)));
+
+        w.push("y");
+        assert_eq!(w.most_frequent(), Some((&"y", 3)));
+    }
+
+    #[test]
+    fn test_array_counter() {
+        let mut w = CountingWindow::<u8, ArrayCounter<u8>>::new(4);
+        for b in "mjqj".bytes() {
+            w.push(b);
+        }
+        assert_eq!(w.distinct(), 3);
+        assert_eq!(w.count(&b'j'), 2);
+        assert_eq!(w.most_frequent(), Some((&b'j', 2)));
+
+        w.push(b'p');
+        assert_eq!(w.distinct(), 3);
+        w.push(b'x');
+        assert!(w.all_distinct());
+    }
+
+    #[test]
+    fn test_counters_agree() {
+        let input = "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg";
+        let mut hashed = CountingWindow::<u8>::new(4);
+        let mut array = CountingWindow::<u8, ArrayCounter<u8>>::new(4);
+        let mut bitmask = BitmaskWindow::<Letter>::new(4);
+
+        for (b, l) in input.bytes().zip(letters(input)) {
+            hashed.push(b);
+            array.push(b);
+            bitmask.push(l);
+            assert_eq!(hashed.distinct(), array.distinct());
+            assert_eq!(hashed.all_distinct(), bitmask.all_distinct());
+        }
+    }
+
+    #[test]
+    fn test_bitmask_window() {
+        let mut w = BitmaskWindow::new(3);
+        for l in letters("aab") {
+            w.push(l);
+        }
+        assert!(!w.all_distinct());
+
+        // three of a kind leaves one bit set
+        let mut w = BitmaskWindow::new(3);
+        for l in letters("aaa") {
+            w.push(l);
+        }
+        assert!(!w.all_distinct());
+
+        w.push(letters("b")[0]);
+        w.push(letters("Z")[0]);
+        assert!(w.all_distinct());
+    }
+
+    #[test]
+    fn test_letter() {
+        assert_eq!(Letter::try_from(b'a').unwrap().index(), 0);
+        assert_eq!(Letter::try_from(b'Z').unwrap().index(), 51);
+        assert_eq!(Letter::try_from(b'1'), Err(b'1'));
+    }
+
+    #[test]
+    #[should_panic(expected = "alphabet is too large")]
+    fn test_bitmask_alphabet_too_large() {
+        BitmaskWindow::<u8>::new(4);
+    }
+}